@@ -0,0 +1,437 @@
+//! Static checks over a parsed [`Program`].
+//!
+//! The control-flow graph has a node per parsed line plus one for running past the last line.
+//! `call` is linked to its callee and every `ret` reachable from that callee is linked back to
+//! the line after the call, so registers written inside a function count as written after it
+//! returns.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+
+use crate::instructions::{Control, Operand};
+use crate::parse::{Arg, Program};
+use crate::Dialect;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+        first: usize,
+    },
+    /// Lines `line..=last` can never be executed
+    Unreachable {
+        line: usize,
+        last: usize,
+    },
+    ReadBeforeWrite {
+        line: usize,
+        register: String,
+    },
+    /// No path starting at this line reaches `end`
+    NeverEnds {
+        line: usize,
+    },
+    RetOutsideCallable {
+        line: usize,
+    },
+}
+
+impl Diagnostic {
+    pub fn line(&self) -> usize {
+        match self {
+            Diagnostic::UndefinedLabel { line, .. }
+            | Diagnostic::DuplicateLabel { line, .. }
+            | Diagnostic::Unreachable { line, .. }
+            | Diagnostic::ReadBeforeWrite { line, .. }
+            | Diagnostic::NeverEnds { line }
+            | Diagnostic::RetOutsideCallable { line } => *line,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::UndefinedLabel { line, label } => {
+                write!(f, "line {line}: undefined label `{label}`")
+            }
+            Diagnostic::DuplicateLabel { line, label, first } => {
+                write!(
+                    f,
+                    "line {line}: label `{label}` is already defined on line {first}"
+                )
+            }
+            Diagnostic::Unreachable { line, last } if line == last => {
+                write!(f, "line {line}: unreachable code")
+            }
+            Diagnostic::Unreachable { line, last } => {
+                write!(f, "lines {line}-{last}: unreachable code")
+            }
+            Diagnostic::ReadBeforeWrite { line, register } => write!(
+                f,
+                "line {line}: register `{register}` may be read before it is assigned"
+            ),
+            Diagnostic::NeverEnds { line } => {
+                write!(f, "line {line}: execution never reaches `end` from here")
+            }
+            Diagnostic::RetOutsideCallable { line } => {
+                write!(f, "line {line}: `ret` outside of any called label")
+            }
+        }
+    }
+}
+
+struct Graph {
+    /// Successors of every line; index `lines.len()` is running past the last line
+    succ: Vec<Vec<usize>>,
+    /// Which `call` targets (as line indices) every line belongs to
+    callables: Vec<BTreeSet<usize>>,
+}
+
+impl Program<'_> {
+    fn label_target(&self, idx: usize) -> Option<usize> {
+        let (inst, args) = self.lines[idx].inst.as_ref()?;
+        let pos = inst.operands.iter().position(|op| *op == Operand::Label)?;
+        self.labels.get(args[pos].name()).copied()
+    }
+
+    /// Successors of a line without descending into calls
+    fn local_succ(&self, idx: usize) -> Vec<usize> {
+        let end = self.lines.len();
+        let Some((inst, args)) = &self.lines[idx].inst else {
+            return match self.dialect {
+                Dialect::Simple => vec![],
+                Dialect::PartII => vec![idx + 1],
+            };
+        };
+        match inst.control {
+            Control::Next | Control::Call => vec![idx + 1],
+            Control::Jump => self.label_target(idx).into_iter().collect(),
+            Control::Branch => self
+                .label_target(idx)
+                .into_iter()
+                .chain([idx + 1])
+                .collect(),
+            Control::Return | Control::End => vec![],
+            Control::Relative => match args[1] {
+                // an offset big enough to overflow can only point past the last line
+                Arg::Imm(offset) => (idx as i64)
+                    .checked_add(offset)
+                    .map_or(Ok(usize::MAX), usize::try_from)
+                    .ok()
+                    .map(|target| target.min(end))
+                    .into_iter()
+                    .chain([idx + 1])
+                    .collect(),
+                _ => (0..=end).collect(),
+            },
+        }
+    }
+
+    fn graph(&self) -> Graph {
+        let end = self.lines.len();
+        let local: Vec<_> = (0..end).map(|idx| self.local_succ(idx)).collect();
+        let mut callables = vec![BTreeSet::new(); end + 1];
+        let mut call_sites: HashMap<usize, Vec<usize>> = HashMap::new();
+        for idx in 0..end {
+            if let (Some((inst, _)), Some(target)) = (&self.lines[idx].inst, self.label_target(idx))
+            {
+                if inst.control == Control::Call {
+                    call_sites.entry(target).or_default().push(idx);
+                }
+            }
+        }
+        for &entry in call_sites.keys() {
+            for idx in reachable([entry], |idx| local.get(idx).map_or(&[][..], Vec::as_slice)) {
+                callables[idx].insert(entry);
+            }
+        }
+
+        let mut succ = local;
+        succ.push(vec![]);
+        for idx in 0..end {
+            let Some((inst, _)) = &self.lines[idx].inst else {
+                continue;
+            };
+            match inst.control {
+                Control::Call => succ[idx] = self.label_target(idx).into_iter().collect(),
+                Control::Return => {
+                    succ[idx] = callables[idx]
+                        .iter()
+                        .flat_map(|entry| &call_sites[entry])
+                        .map(|site| site + 1)
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+        Graph { succ, callables }
+    }
+
+    /// Registers read and written by a line
+    fn registers(&self, idx: usize) -> (Vec<String>, Vec<String>) {
+        let (mut reads, mut writes) = (vec![], vec![]);
+        if let Some((inst, args)) = &self.lines[idx].inst {
+            for (op, arg) in inst.operands.iter().zip(args) {
+                match (op, arg) {
+                    (Operand::Register, arg) => writes.push(arg.name().to_string()),
+                    (Operand::Update, arg) => {
+                        reads.push(arg.name().to_string());
+                        writes.push(arg.name().to_string());
+                    }
+                    (Operand::Value, Arg::Reg(name)) => reads.push(name.to_string()),
+                    // a list takes every remaining operand
+                    (Operand::List, _) => {
+                        reads.extend(args[inst.operands.len() - 1..].iter().filter_map(|arg| {
+                            match arg {
+                                Arg::Reg(name) => Some(name.to_string()),
+                                _ => None,
+                            }
+                        }))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        (reads, writes)
+    }
+}
+
+fn reachable<'g>(
+    from: impl IntoIterator<Item = usize>,
+    succ: impl Fn(usize) -> &'g [usize],
+) -> BTreeSet<usize> {
+    let mut seen = BTreeSet::new();
+    let mut queue: VecDeque<usize> = from.into_iter().collect();
+    while let Some(idx) = queue.pop_front() {
+        if seen.insert(idx) {
+            queue.extend(succ(idx));
+        }
+    }
+    seen
+}
+
+/// Reports likely mistakes in `program`, ordered by line
+pub fn analyze(program: &Program) -> Vec<Diagnostic> {
+    let lines = &program.lines;
+    let end = lines.len();
+    let number = |idx: usize| lines[idx].number;
+    let mut diagnostics = vec![];
+
+    let mut defined: HashMap<&str, usize> = HashMap::new();
+    for line in lines {
+        if let Some(label) = line.label {
+            if let Some(&first) = defined.get(label) {
+                diagnostics.push(Diagnostic::DuplicateLabel {
+                    line: line.number,
+                    label: label.to_string(),
+                    first,
+                });
+            } else {
+                defined.insert(label, line.number);
+            }
+        }
+        if let Some((inst, args)) = &line.inst {
+            for (op, arg) in inst.operands.iter().zip(args) {
+                if *op == Operand::Label && !program.labels.contains_key(arg.name()) {
+                    diagnostics.push(Diagnostic::UndefinedLabel {
+                        line: line.number,
+                        label: arg.name().to_string(),
+                    });
+                }
+            }
+        }
+    }
+    if end == 0 {
+        return diagnostics;
+    }
+
+    let Graph { succ, callables } = program.graph();
+    let live = reachable([0], |idx| &succ[idx]);
+
+    let mut idx = 0;
+    while idx < end {
+        if live.contains(&idx) {
+            idx += 1;
+            continue;
+        }
+        let first = idx;
+        while idx < end && !live.contains(&idx) {
+            idx += 1;
+        }
+        diagnostics.push(Diagnostic::Unreachable {
+            line: number(first),
+            last: number(idx - 1),
+        });
+    }
+
+    let mut pred = vec![vec![]; end + 1];
+    for &idx in &live {
+        for &next in &succ[idx] {
+            pred[next].push(idx);
+        }
+    }
+
+    let finished = (0..end)
+        .filter(|&idx| matches!(&lines[idx].inst, Some((inst, _)) if inst.control == Control::End));
+    let finishes = match program.dialect {
+        Dialect::Simple => reachable(finished.chain([end]), |idx| &pred[idx]),
+        Dialect::PartII => reachable(finished, |idx| &pred[idx]),
+    };
+    for &idx in live.iter().filter(|&&idx| idx < end) {
+        if !finishes.contains(&idx) && (idx == 0 || pred[idx].iter().any(|p| finishes.contains(p)))
+        {
+            diagnostics.push(Diagnostic::NeverEnds { line: number(idx) });
+        }
+        if callables[idx].is_empty()
+            && matches!(&lines[idx].inst, Some((inst, _)) if inst.control == Control::Return)
+        {
+            diagnostics.push(Diagnostic::RetOutsideCallable { line: number(idx) });
+        }
+    }
+
+    // registers definitely assigned when entering every line, `None` until first visited
+    let mut assigned: Vec<Option<BTreeSet<String>>> = vec![None; end + 1];
+    assigned[0] = Some(BTreeSet::new());
+    let mut queue = VecDeque::from([0]);
+    while let Some(idx) = queue.pop_front() {
+        if idx == end {
+            continue;
+        }
+        let mut out = assigned[idx].clone().unwrap();
+        out.extend(program.registers(idx).1);
+        for &next in &succ[idx] {
+            let merged = match &assigned[next] {
+                None => out.clone(),
+                Some(current) => current.intersection(&out).cloned().collect(),
+            };
+            if assigned[next].as_ref() != Some(&merged) {
+                assigned[next] = Some(merged);
+                queue.push_back(next);
+            }
+        }
+    }
+    for &idx in live.iter().filter(|&&idx| idx < end) {
+        let before = assigned[idx].as_ref().unwrap();
+        let mut reported = BTreeSet::new();
+        for register in program.registers(idx).0 {
+            if !before.contains(&register) && reported.insert(register.clone()) {
+                diagnostics.push(Diagnostic::ReadBeforeWrite {
+                    line: number(idx),
+                    register,
+                });
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(Diagnostic::line);
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn check(source: &str) -> Vec<String> {
+        analyze(&parse(source, Dialect::PartII))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn clean_program() {
+        let source = "\nmov   a, 5\nmov   b, a\nmov   c, a\ncall  proc_fact\ncall  print\nend\n\nproc_fact:\n    dec   b\n    mul   c, b\n    cmp   b, 1\n    jne   proc_fact\n    ret\n\nprint:\n    msg   a, '! = ', c ; output text\n    ret\n";
+        assert_eq!(check(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn writes_inside_calls_are_visible_after_return() {
+        let source = "mov a, 11\ncall set_d\nmsg 'd = ', d\nend\n\nset_d:\n    mov d, a\n    ret\n";
+        assert_eq!(check(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn labels() {
+        let source = "jmp strat\nstart:\nend\nstart:\n";
+        assert_eq!(
+            check(source),
+            [
+                "line 1: undefined label `strat`",
+                "line 1: execution never reaches `end` from here",
+                "lines 2-4: unreachable code",
+                "line 4: label `start` is already defined on line 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = "mov a, 1\nend\ninc a\ndec a\n";
+        assert_eq!(check(source), ["lines 3-4: unreachable code"]);
+    }
+
+    #[test]
+    fn read_before_write() {
+        let source = "mov a, 1\ncmp a, 0\nje skip\nmov b, 2\nskip:\nadd a, b\nmsg a, c\nend\n";
+        assert_eq!(
+            check(source),
+            [
+                "line 6: register `b` may be read before it is assigned",
+                "line 7: register `c` may be read before it is assigned",
+            ]
+        );
+    }
+
+    #[test]
+    fn never_ends() {
+        let source = "call func1\ncall print\nend\n\nfunc1:\n    call  func2\n    ret\n\nfunc2:\n    ret\n\nprint:\n    msg 'This program should return null'\n";
+        assert_eq!(
+            check(source),
+            [
+                "line 1: execution never reaches `end` from here",
+                "line 3: unreachable code",
+            ]
+        );
+
+        let source = "mov a, 1\ncmp a, 0\njne forever\nend\nforever:\njmp forever\n";
+        assert_eq!(
+            check(source),
+            ["line 5: execution never reaches `end` from here"]
+        );
+    }
+
+    #[test]
+    fn ret_outside_callable() {
+        assert_eq!(
+            check("mov a, 1\nret\n"),
+            [
+                "line 1: execution never reaches `end` from here",
+                "line 2: `ret` outside of any called label",
+            ]
+        );
+    }
+
+    #[test]
+    fn simple_dialect() {
+        let program = parse("mov a 5\ndec a\njnz a -1\ninc b", Dialect::Simple);
+        assert_eq!(
+            analyze(&program),
+            [Diagnostic::ReadBeforeWrite {
+                line: 4,
+                register: "b".to_string()
+            }]
+        );
+        let program = parse(
+            "mov a 1\njnz a 9223372036854775807\nmov b 2",
+            Dialect::Simple,
+        );
+        assert!(analyze(&program).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+//! `asmfmt [--check] FILE...`
+//!
+//! Rewrites every file with its formatted source. With `--check` nothing is written, files
+//! which aren't formatted yet are listed and the exit status is 1 if there are any.
+
+use std::process::ExitCode;
+use std::{env, fs};
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        eprintln!("usage: asmfmt [--check] FILE...");
+        return ExitCode::from(2);
+    }
+
+    let mut unformatted = false;
+    for file in files {
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{file}: {e}");
+                return ExitCode::from(2);
+            }
+        };
+        if assembler::is_formatted(&source) {
+            continue;
+        }
+        if check {
+            println!("{file}");
+            unformatted = true;
+        } else if let Err(e) = fs::write(&file, assembler::format(&source)) {
+            eprintln!("{file}: {e}");
+            return ExitCode::from(2);
+        }
+    }
+    if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
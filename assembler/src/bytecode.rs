@@ -0,0 +1,438 @@
+//! A compiled form of [`Program`] for running hot loops.
+//!
+//! Registers live in fixed slots instead of a `HashMap`: `a`..`z` always take slots `0..26`,
+//! any other name gets the next free one. Labels and relative `jnz` offsets are resolved into
+//! op indices at compile time.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+
+use crate::instructions::BinOp;
+use crate::parse::{Arg, Program};
+use crate::{Dialect, Outcome};
+
+pub(crate) type Slot = u16;
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Src {
+    Reg(Slot),
+    Imm(i64),
+}
+
+#[derive(Debug)]
+pub(crate) enum MsgPart {
+    Text(String),
+    Value(Src),
+}
+
+#[derive(Debug)]
+pub(crate) enum Op {
+    Mov(Slot, Src),
+    Inc(Slot),
+    Dec(Slot),
+    Binary(BinOp, Slot, Src),
+    Not(Slot),
+    Push(Src),
+    Pop(Slot),
+    In(Slot),
+    Out(Src),
+    Msg(Box<[MsgPart]>),
+    Jmp(usize),
+    Cmp(Src, Src),
+    JumpIf(fn(Ordering) -> bool, usize),
+    Jnz(Src, usize),
+    /// `jnz` whose offset is only known at runtime, relative to the given source line
+    JnzBy(Src, Src, usize),
+    Call(usize),
+    Ret,
+    Nop,
+    End,
+    /// Terminates the program without an outcome
+    Abort,
+}
+
+pub struct Bytecode {
+    ops: Vec<Op>,
+    /// Register name of every slot
+    names: Vec<String>,
+    /// Op index of every source line, plus one past the last line
+    lines: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// More distinct register names than there are slots
+    TooManyRegisters { line: usize, register: String },
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::TooManyRegisters { line, register } => write!(
+                f,
+                "line {line}: no slot left for register `{register}`, at most {} names fit",
+                Slot::MAX as usize + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+pub(crate) struct Compiler<'p> {
+    labels: &'p HashMap<&'p str, usize>,
+    lines: &'p [usize],
+    line: usize,
+    names: Vec<String>,
+    /// The first register name that didn't get a slot, reported once its line is compiled
+    overflow: Option<String>,
+}
+
+impl Compiler<'_> {
+    pub fn reg(&mut self, name: &str) -> Slot {
+        let mut chars = name.chars();
+        if let (Some(c @ 'a'..='z'), None) = (chars.next(), chars.next()) {
+            return (c as u8 - b'a') as Slot;
+        }
+        let slot = match self.names.iter().skip(26).position(|n| n == name) {
+            Some(idx) => 26 + idx,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            }
+        };
+        Slot::try_from(slot).unwrap_or_else(|_| {
+            self.overflow.get_or_insert_with(|| name.to_string());
+            Slot::MAX
+        })
+    }
+
+    pub fn src(&mut self, x: &Arg) -> Src {
+        match x {
+            Arg::Reg(name) => Src::Reg(self.reg(name)),
+            Arg::Imm(v) => Src::Imm(*v),
+            arg => panic!("{arg:?} is not a value"),
+        }
+    }
+
+    pub fn label(&self, lbl: &str) -> Option<usize> {
+        self.labels.get(lbl).map(|&line| self.lines[line])
+    }
+
+    pub fn jump_if(&self, lbl: &str, cond: fn(Ordering) -> bool) -> Op {
+        self.label(lbl)
+            .map_or(Op::Abort, |target| Op::JumpIf(cond, target))
+    }
+
+    pub fn jnz(&mut self, x: &Arg, offset: &Arg) -> Op {
+        let cond = self.src(x);
+        match self.src(offset) {
+            Src::Imm(offset) => match target(self.lines, self.line, offset) {
+                Some(target) => Op::Jnz(cond, target),
+                None => Op::JnzBy(cond, Src::Imm(offset), self.line),
+            },
+            offset => Op::JnzBy(cond, offset, self.line),
+        }
+    }
+}
+
+/// Op index `offset` lines away from `line`, `None` if that's before the first line
+#[inline]
+fn target(lines: &[usize], line: usize, offset: i64) -> Option<usize> {
+    // an offset big enough to overflow can only point past the last line
+    let line = (line as i64)
+        .checked_add(offset)
+        .map_or(Ok(usize::MAX), usize::try_from)
+        .ok()?;
+    Some(lines[line.min(lines.len() - 1)])
+}
+
+/// Lowers every line of `program` into an op. Lines the dialect would skip are dropped, lines
+/// it would abort on become [`Op::Abort`]. Fails if the program names more registers than
+/// there are slots.
+pub fn compile(program: &Program) -> Result<Bytecode, CompileError> {
+    let mut lines = Vec::with_capacity(program.lines.len() + 1);
+    let mut count = 0;
+    for line in &program.lines {
+        lines.push(count);
+        if line.inst.is_some() || program.dialect == Dialect::Simple {
+            count += 1;
+        }
+    }
+    lines.push(count);
+
+    let mut compiler = Compiler {
+        labels: &program.labels,
+        lines: &lines,
+        line: 0,
+        names: ('a'..='z').map(String::from).collect(),
+        overflow: None,
+    };
+    let mut ops = Vec::with_capacity(count + 1);
+    for (idx, line) in program.lines.iter().enumerate() {
+        compiler.line = idx;
+        match &line.inst {
+            Some((inst, args)) => ops.push((inst.compile)(&mut compiler, args)),
+            None if program.dialect == Dialect::Simple => ops.push(Op::Abort),
+            None => {}
+        }
+        if let Some(register) = compiler.overflow.take() {
+            return Err(CompileError::TooManyRegisters {
+                line: line.number,
+                register,
+            });
+        }
+    }
+    // running past the last line
+    ops.push(match program.dialect {
+        Dialect::Simple => Op::End,
+        Dialect::PartII => Op::Abort,
+    });
+    let names = compiler.names;
+    Ok(Bytecode { ops, names, lines })
+}
+
+#[inline(always)]
+fn get(regs: &[Option<i64>], src: Src) -> i64 {
+    match src {
+        Src::Reg(slot) => regs[slot as usize].expect("register is empty but is requested"),
+        Src::Imm(v) => v,
+    }
+}
+
+/// Same as [`run`](crate::run) but over compiled bytecode
+pub fn execute(code: &Bytecode, input: &[i64]) -> Option<Outcome> {
+    let mut regs: Vec<Option<i64>> = vec![None; code.names.len()];
+    let mut calls = vec![];
+    let mut stack = vec![];
+    let mut input = input.iter();
+    let mut output: Option<String> = None;
+    let mut last_cmp = None;
+    let mut pc = 0;
+    loop {
+        match &code.ops[pc] {
+            Op::Mov(r, src) => regs[*r as usize] = Some(get(&regs, *src)),
+            Op::Inc(r) => {
+                if let Some(v) = &mut regs[*r as usize] {
+                    *v = v.checked_add(1)?;
+                }
+            }
+            Op::Dec(r) => {
+                if let Some(v) = &mut regs[*r as usize] {
+                    *v = v.checked_sub(1)?;
+                }
+            }
+            Op::Binary(op, r, src) => {
+                let val = get(&regs, *src);
+                if let Some(v) = &mut regs[*r as usize] {
+                    *v = op.apply(*v, val)?;
+                }
+            }
+            Op::Not(r) => {
+                if let Some(v) = &mut regs[*r as usize] {
+                    *v = !*v;
+                }
+            }
+            Op::Push(src) => stack.push(get(&regs, *src)),
+            Op::Pop(r) => regs[*r as usize] = Some(stack.pop()?),
+            Op::In(r) => regs[*r as usize] = Some(*input.next()?),
+            Op::Out(src) => {
+                let val = get(&regs, *src);
+                _ = write!(output.get_or_insert_with(String::new), "{val}");
+            }
+            Op::Msg(parts) => {
+                let out = output.get_or_insert_with(String::new);
+                for part in parts.iter() {
+                    match part {
+                        MsgPart::Text(txt) => out.push_str(txt),
+                        MsgPart::Value(src) => _ = write!(out, "{}", get(&regs, *src)),
+                    }
+                }
+            }
+            Op::Jmp(target) => {
+                pc = *target;
+                continue;
+            }
+            Op::Cmp(x, y) => last_cmp = Some(get(&regs, *x).cmp(&get(&regs, *y))),
+            Op::JumpIf(cond, target) => {
+                if cond(last_cmp.take()?) {
+                    pc = *target;
+                    continue;
+                }
+            }
+            Op::Jnz(x, target) => {
+                if get(&regs, *x) != 0 {
+                    pc = *target;
+                    continue;
+                }
+            }
+            Op::JnzBy(x, offset, line) => {
+                if get(&regs, *x) != 0 {
+                    pc = target(&code.lines, *line, get(&regs, *offset))?;
+                    continue;
+                }
+            }
+            Op::Call(target) => {
+                calls.push(pc + 1);
+                pc = *target;
+                continue;
+            }
+            Op::Ret => {
+                pc = calls.pop()?;
+                continue;
+            }
+            Op::Nop => {}
+            Op::End => break,
+            Op::Abort => return None,
+        }
+        pc += 1;
+    }
+    Some(Outcome {
+        registers: regs
+            .into_iter()
+            .zip(&code.names)
+            .filter_map(|(v, name)| Some((name.clone(), v?)))
+            .collect(),
+        output,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{parse, run};
+
+    const FACTORIAL: &str = "
+mov   n, 20000        ; repetitions
+outer:
+    mov   a, 20
+    mov   f, 1
+inner:
+    mul   f, a
+    dec   a
+    cmp   a, 1
+    jg    inner
+    dec   n
+    cmp   n, 0
+    jne   outer
+msg   '20! = ', f
+end
+";
+
+    const FIBONACCI: &str = "
+mov   n, 20000        ; repetitions
+outer:
+    mov   a, 0
+    mov   b, 1
+    mov   c, 90
+    call  fib
+    dec   n
+    cmp   n, 0
+    jne   outer
+msg   'fib(90) = ', a
+end
+
+fib:
+    mov   t, a
+    add   t, b
+    mov   a, b
+    mov   b, t
+    dec   c
+    cmp   c, 0
+    jne   fib
+    ret
+";
+
+    fn assert_same(source: &str, dialect: Dialect, input: &[i64]) {
+        let program = parse(source, dialect);
+        assert_eq!(
+            execute(&compile(&program).unwrap(), input),
+            run(&program, input),
+            "{source}"
+        );
+    }
+
+    #[test]
+    fn matches_interpreter() {
+        assert_same(&FACTORIAL.replace("20000", "3"), Dialect::PartII, &[]);
+        assert_same(&FIBONACCI.replace("20000", "3"), Dialect::PartII, &[]);
+        assert_same(
+            "mov c 12\nmov b 0\nmov a 200\ndec a\ninc b\njnz a -2\ndec c\nmov a b\njnz c -5\njnz 0 1\nmov c a",
+            Dialect::Simple,
+            &[],
+        );
+        assert_same("mov a 2\njnz a 5\nmov b 1", Dialect::Simple, &[]);
+        assert_same("mov a 2\njnz a -5\nmov b 1", Dialect::Simple, &[]);
+        assert_same("mov a 5\nnope\ninc a", Dialect::Simple, &[]);
+        assert_same(
+            "in a\nin b\npush a\nmod a, b\nshl a, 3\npop c\nnot c\nout c\nmsg ' ', a\nend",
+            Dialect::PartII,
+            &[17, 5],
+        );
+        assert_same("in a\nend", Dialect::PartII, &[]);
+        assert_same("call nowhere\nend", Dialect::PartII, &[]);
+        assert_same("mov a, 1\nmsg a", Dialect::PartII, &[]);
+    }
+
+    #[test]
+    fn extended_register_names_get_own_slots() {
+        let mut compiler = Compiler {
+            labels: &HashMap::new(),
+            lines: &[0],
+            line: 0,
+            names: ('a'..='z').map(String::from).collect(),
+            overflow: None,
+        };
+        assert_eq!(compiler.reg("c"), 2);
+        assert_eq!(compiler.reg("count"), 26);
+        assert_eq!(compiler.reg("idx"), 27);
+        assert_eq!(compiler.reg("count"), 26);
+    }
+
+    #[test]
+    fn running_out_of_slots() {
+        let mut compiler = Compiler {
+            labels: &HashMap::new(),
+            lines: &[0],
+            line: 0,
+            names: (0..=Slot::MAX).map(|n| format!("r{n}")).collect(),
+            overflow: None,
+        };
+        assert_eq!(compiler.reg("r65535"), Slot::MAX);
+        assert_eq!(compiler.overflow, None);
+        compiler.reg("extra");
+        compiler.reg("another");
+        assert_eq!(compiler.overflow.as_deref(), Some("extra"));
+        let error = CompileError::TooManyRegisters {
+            line: 3,
+            register: "extra".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "line 3: no slot left for register `extra`, at most 65536 names fit"
+        );
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_against_interpreter() {
+        for (name, source) in [("factorial", FACTORIAL), ("fibonacci", FIBONACCI)] {
+            let program = parse(source, Dialect::PartII);
+            let start = Instant::now();
+            let expected = run(&program, &[]);
+            let interpreted = start.elapsed();
+            let start = Instant::now();
+            let actual = execute(&compile(&program).unwrap(), &[]);
+            let compiled = start.elapsed();
+            assert_eq!(actual, expected);
+            println!(
+                "{name}: interpreter {interpreted:?}, bytecode {compiled:?} ({:.1}x)",
+                interpreted.as_secs_f64() / compiled.as_secs_f64()
+            );
+        }
+    }
+}
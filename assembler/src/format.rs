@@ -0,0 +1,195 @@
+//! Canonical layout for assembler sources.
+//!
+//! Labels start at column 0 and everything after a label is indented by four spaces.
+//! Operands start at the same column on every line, are separated by `, ` and keep their
+//! source spelling. Trailing comments are aligned within each paragraph, runs of blank lines
+//! are collapsed. Lines that don't lex are kept as they are, trimmed. Formatting only moves
+//! whitespace, commas and comments around, so it never changes what a line parses to.
+
+use crate::lexer::{lex_spanned, Token};
+
+const INDENT: &str = "    ";
+
+enum Item<'a> {
+    Blank,
+    Comment(&'a str),
+    Label(&'a str, Option<&'a str>),
+    Code(&'a str, Vec<&'a str>, Option<&'a str>),
+    Verbatim(&'a str),
+}
+
+fn item(line: &str) -> Item<'_> {
+    let line = line.trim();
+    let Ok((tokens, comment)) = lex_spanned(line) else {
+        return Item::Verbatim(line);
+    };
+    match &tokens[..] {
+        [] => match comment {
+            None => Item::Blank,
+            Some(comment) => Item::Comment(comment),
+        },
+        [(Token::Ident(label), _), (Token::Colon, _)] => Item::Label(label, comment),
+        [(Token::Ident(name), _), rest @ ..] if rest.iter().all(|(t, _)| *t != Token::Colon) => {
+            Item::Code(name, rest.iter().map(|(_, text)| *text).collect(), comment)
+        }
+        _ => Item::Verbatim(line),
+    }
+}
+
+fn comment(text: &str) -> String {
+    match text.trim() {
+        "" => ";".to_string(),
+        text => format!("; {text}"),
+    }
+}
+
+/// Lays `source` out canonically, see the module docs
+pub fn format(source: &str) -> String {
+    let mut items = vec![];
+    for item in source.lines().map(item) {
+        let blank_before = matches!(items.last(), None | Some(Item::Blank));
+        if !(matches!(item, Item::Blank) && blank_before) {
+            items.push(item);
+        }
+    }
+    while matches!(items.last(), Some(Item::Blank)) {
+        items.pop();
+    }
+
+    let width = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Code(name, ..) => Some(name.len()),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+        .max(4)
+        + 2;
+
+    // code of every line, without indentation and comments
+    let mut in_body = false;
+    let mut lines: Vec<(bool, String, Option<&str>)> = vec![];
+    for item in &items {
+        let (code, trailing) = match item {
+            Item::Blank => (String::new(), None),
+            Item::Comment(text) => (comment(text), None),
+            Item::Label(label, trailing) => {
+                in_body = false;
+                (format!("{label}:"), *trailing)
+            }
+            Item::Code(name, operands, trailing) if operands.is_empty() => {
+                (name.to_string(), *trailing)
+            }
+            Item::Code(name, operands, trailing) => {
+                (format!("{name:width$}{}", operands.join(", ")), *trailing)
+            }
+            Item::Verbatim(line) => (line.to_string(), None),
+        };
+        lines.push((in_body, code, trailing));
+        if matches!(item, Item::Label(..)) {
+            in_body = true;
+        }
+    }
+
+    // paragraphs are separated by blank lines and labels
+    let mut paragraph = 0;
+    let mut paragraphs = Vec::with_capacity(items.len());
+    for item in &items {
+        if matches!(item, Item::Blank | Item::Label(..)) {
+            paragraph += 1;
+        }
+        paragraphs.push(paragraph);
+    }
+    let mut columns = vec![0; paragraph + 1];
+    for ((indented, code, trailing), &paragraph) in lines.iter().zip(&paragraphs) {
+        if trailing.is_some() {
+            let len = code.len() + if *indented { INDENT.len() } else { 0 };
+            columns[paragraph] = columns[paragraph].max(len + 2);
+        }
+    }
+
+    let mut output = String::new();
+    for ((indented, code, trailing), &paragraph) in lines.iter().zip(&paragraphs) {
+        let mut line = if *indented && !code.is_empty() {
+            format!("{INDENT}{code}")
+        } else {
+            code.clone()
+        };
+        if let Some(text) = trailing {
+            let column = columns[paragraph];
+            line = format!("{line:column$}{}", comment(text));
+        }
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Whether `source` is already laid out the way [`format`] would
+pub fn is_formatted(source: &str) -> bool {
+    format(source) == source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, run, Dialect};
+
+    const PROGRAMS: &[&str] = &[
+        "\n; My first program\nmov  a, 5\ninc  a\ncall function\nmsg  '(5+1)/2 = ', a    ; output message\nend\n\nfunction:\n    div  a, 2\n    ret\n",
+        "\nmov   a, 8            ; value\nmov   b, 0            ; next\nmov   c, 0            ; counter\nmov   d, 0            ; first\nmov   e, 1            ; second\ncall  proc_fib\ncall  print\nend\n\nproc_fib:\n    cmp   c, 2\n    jl    func_0\n    mov   b, d\n    add   b, e\n    mov   d, e\n    mov   e, b\n    inc   c\n    cmp   c, a\n    jle   proc_fib\n    ret\n\nfunc_0:\n    mov   b, c\n    inc   c\n    jmp   proc_fib\n\nprint:\n    msg   'Term ', a, ' of Fibonacci series is: ', b        ; output text\n    ret\n",
+        "\nmov   a, 11           ; value1\nmov   b, 3            ; value2\ncall  mod_func\nmsg   'mod(', a, ', ', b, ') = ', d        ; output\nend\n\n; Mod function\nmod_func:\n    mov   c, a        ; temp1\n    div   c, b\n    mul   c, b\n    mov   d, a        ; temp2\n    sub   d, c\n    ret\n",
+        "\tmov\ta,0x10 ;hex\n\n\n\tpush a\n  pop   counter\nmsg 'a;b',counter,\"\\n\"\t;;\nend\n\n\n",
+    ];
+
+    #[test]
+    fn layout() {
+        let source = "\n\n; setup\nmov a,5 ; five\n  call   half;halve it\nmsg 'a = ' ,a\nend\n\n\nhalf:\n\tdiv a, 2   ;\n  ;done\n\tret\n\n";
+        let expected = "\
+; setup
+mov   a, 5  ; five
+call  half  ; halve it
+msg   'a = ', a
+end
+
+half:
+    div   a, 2  ;
+    ; done
+    ret
+";
+        assert_eq!(format(source), expected);
+        assert!(is_formatted(expected));
+        assert!(!is_formatted(source));
+    }
+
+    #[test]
+    fn keeps_unlexable_lines() {
+        assert_eq!(format("  mov a, $x  \nend"), "mov a, $x\nend\n");
+    }
+
+    #[test]
+    fn idempotent() {
+        for source in PROGRAMS {
+            let formatted = format(source);
+            assert_eq!(format(&formatted), formatted, "{formatted}");
+        }
+    }
+
+    #[test]
+    fn preserves_behaviour() {
+        for source in PROGRAMS {
+            let formatted = format(source);
+            assert_eq!(
+                run(&parse(&formatted, Dialect::PartII), &[]),
+                run(&parse(source, Dialect::PartII), &[]),
+                "{formatted}"
+            );
+        }
+        let source = "mov a 5\ninc a\ndec a\ndec a\njnz a -1\ninc a";
+        assert_eq!(
+            run(&parse(&format(source), Dialect::Simple), &[]),
+            run(&parse(source, Dialect::Simple), &[])
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+
+use crate::bytecode::{Compiler, MsgPart, Op};
+use crate::machine::{Flow, Machine};
+use crate::parse::Arg;
+use crate::Dialect;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Operand {
+    /// A register which is written by the instruction
+    Register,
+    /// A register which is read and then written back
+    Update,
+    /// Either a register or an integer literal
+    Value,
+    Label,
+    /// Any number of string literals and values, only allowed last
+    List,
+}
+
+use Operand::*;
+
+/// Where execution may continue after an instruction, used for static analysis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Control {
+    Next,
+    /// Unconditional jump to the label operand
+    Jump,
+    /// Either the label operand or the next line
+    Branch,
+    /// The label operand, then the next line once the callee returns
+    Call,
+    Return,
+    End,
+    /// Either the next line or the line that many lines away from the current one
+    Relative,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+impl BinOp {
+    /// `None` on overflow or a zero divisor, which aborts the program
+    #[inline(always)]
+    pub fn apply(self, x: i64, y: i64) -> Option<i64> {
+        match self {
+            BinOp::Add => x.checked_add(y),
+            BinOp::Sub => x.checked_sub(y),
+            BinOp::Mul => x.checked_mul(y),
+            BinOp::Div => x.checked_div(y),
+            BinOp::Mod => x.checked_rem(y),
+            BinOp::And => Some(x & y),
+            BinOp::Or => Some(x | y),
+            BinOp::Xor => Some(x ^ y),
+            BinOp::Shl => Some(x.wrapping_shl(y as u32)),
+            BinOp::Shr => Some(x.wrapping_shr(y as u32)),
+        }
+    }
+}
+
+pub(crate) struct Instruction {
+    pub name: &'static str,
+    pub operands: &'static [Operand],
+    pub control: Control,
+    /// Runs the instruction directly from its decoded operands
+    pub exec: for<'a> fn(&mut Machine<'a>, &[Arg<'a>]) -> Option<Flow>,
+    /// Lowers the instruction into a single bytecode op
+    pub compile: fn(&mut Compiler, &[Arg]) -> Op,
+}
+
+impl Dialect {
+    /// Every mnemonic the dialect understands
+    pub(crate) fn instructions(self) -> &'static [Instruction] {
+        match self {
+            Dialect::Simple => SIMPLE,
+            Dialect::PartII => PART_II,
+        }
+    }
+}
+
+const SIMPLE: &[Instruction] = &[MOV, INC, DEC, JNZ];
+
+const PART_II: &[Instruction] = &[
+    MOV,
+    INC,
+    DEC,
+    Instruction {
+        name: "add",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Add),
+        compile: |c, a| Op::Binary(BinOp::Add, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "sub",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Sub),
+        compile: |c, a| Op::Binary(BinOp::Sub, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "mul",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Mul),
+        compile: |c, a| Op::Binary(BinOp::Mul, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "div",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Div),
+        compile: |c, a| Op::Binary(BinOp::Div, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "mod",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Mod),
+        compile: |c, a| Op::Binary(BinOp::Mod, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "and",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::And),
+        compile: |c, a| Op::Binary(BinOp::And, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "or",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Or),
+        compile: |c, a| Op::Binary(BinOp::Or, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "xor",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Xor),
+        compile: |c, a| Op::Binary(BinOp::Xor, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "shl",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Shl),
+        compile: |c, a| Op::Binary(BinOp::Shl, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "shr",
+        operands: &[Update, Value],
+        control: Control::Next,
+        exec: |m, a| m.binary(a, BinOp::Shr),
+        compile: |c, a| Op::Binary(BinOp::Shr, c.reg(a[0].name()), c.src(&a[1])),
+    },
+    Instruction {
+        name: "not",
+        operands: &[Update],
+        control: Control::Next,
+        exec: |m, a| m.update(a[0].name(), |v| Some(!v)),
+        compile: |c, a| Op::Not(c.reg(a[0].name())),
+    },
+    Instruction {
+        name: "push",
+        operands: &[Value],
+        control: Control::Next,
+        exec: |m, a| {
+            let val = m.value(&a[0]);
+            m.stack.push(val);
+            Some(Flow::Next)
+        },
+        compile: |c, a| Op::Push(c.src(&a[0])),
+    },
+    Instruction {
+        name: "pop",
+        operands: &[Register],
+        control: Control::Next,
+        exec: |m, a| {
+            let val = m.stack.pop()?;
+            m.registers.insert(a[0].name(), val);
+            Some(Flow::Next)
+        },
+        compile: |c, a| Op::Pop(c.reg(a[0].name())),
+    },
+    Instruction {
+        name: "in",
+        operands: &[Register],
+        control: Control::Next,
+        exec: |m, a| {
+            let val = m.input.pop_front()?;
+            m.registers.insert(a[0].name(), val);
+            Some(Flow::Next)
+        },
+        compile: |c, a| Op::In(c.reg(a[0].name())),
+    },
+    Instruction {
+        name: "out",
+        operands: &[Value],
+        control: Control::Next,
+        exec: |m, a| {
+            let val = m.value(&a[0]);
+            m.print(&val.to_string());
+            Some(Flow::Next)
+        },
+        compile: |c, a| Op::Out(c.src(&a[0])),
+    },
+    Instruction {
+        name: "nop",
+        operands: &[],
+        control: Control::Next,
+        exec: |_, _| Some(Flow::Next),
+        compile: |_, _| Op::Nop,
+    },
+    Instruction {
+        name: "jmp",
+        operands: &[Label],
+        control: Control::Jump,
+        exec: |m, a| m.label(a[0].name()),
+        compile: |c, a| c.label(a[0].name()).map_or(Op::Abort, Op::Jmp),
+    },
+    Instruction {
+        name: "cmp",
+        operands: &[Value, Value],
+        control: Control::Next,
+        exec: |m, a| {
+            m.last_cmp = Some(m.value(&a[0]).cmp(&m.value(&a[1])));
+            Some(Flow::Next)
+        },
+        compile: |c, a| Op::Cmp(c.src(&a[0]), c.src(&a[1])),
+    },
+    Instruction {
+        name: "jne",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_ne),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_ne),
+    },
+    Instruction {
+        name: "je",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_eq),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_eq),
+    },
+    Instruction {
+        name: "jge",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_ge),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_ge),
+    },
+    Instruction {
+        name: "jg",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_gt),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_gt),
+    },
+    Instruction {
+        name: "jle",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_le),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_le),
+    },
+    Instruction {
+        name: "jl",
+        operands: &[Label],
+        control: Control::Branch,
+        exec: |m, a| m.jump_if(a[0].name(), Ordering::is_lt),
+        compile: |c, a| c.jump_if(a[0].name(), Ordering::is_lt),
+    },
+    Instruction {
+        name: "call",
+        operands: &[Label],
+        control: Control::Call,
+        exec: |m, a| {
+            m.calls.push(m.idx + 1);
+            m.label(a[0].name())
+        },
+        compile: |c, a| c.label(a[0].name()).map_or(Op::Abort, Op::Call),
+    },
+    Instruction {
+        name: "ret",
+        operands: &[],
+        control: Control::Return,
+        exec: |m, _| Some(Flow::Jump(m.calls.pop()?)),
+        compile: |_, _| Op::Ret,
+    },
+    Instruction {
+        name: "msg",
+        operands: &[List],
+        control: Control::Next,
+        exec: |m, a| {
+            let out_part = a
+                .iter()
+                .map(|arg| match arg {
+                    Arg::Text(txt) => txt.clone(),
+                    arg => m.value(arg).to_string(),
+                })
+                .collect::<String>();
+            m.print(&out_part);
+            Some(Flow::Next)
+        },
+        compile: |c, a| {
+            let parts = a
+                .iter()
+                .map(|arg| match arg {
+                    Arg::Text(txt) => MsgPart::Text(txt.clone()),
+                    arg => MsgPart::Value(c.src(arg)),
+                })
+                .collect();
+            Op::Msg(parts)
+        },
+    },
+    Instruction {
+        name: "end",
+        operands: &[],
+        control: Control::End,
+        exec: |_, _| Some(Flow::End),
+        compile: |_, _| Op::End,
+    },
+];
+
+const MOV: Instruction = Instruction {
+    name: "mov",
+    operands: &[Register, Value],
+    control: Control::Next,
+    exec: |m, a| {
+        let val = m.value(&a[1]);
+        m.registers.insert(a[0].name(), val);
+        Some(Flow::Next)
+    },
+    compile: |c, a| Op::Mov(c.reg(a[0].name()), c.src(&a[1])),
+};
+
+const INC: Instruction = Instruction {
+    name: "inc",
+    operands: &[Update],
+    control: Control::Next,
+    exec: |m, a| m.update(a[0].name(), |v| v.checked_add(1)),
+    compile: |c, a| Op::Inc(c.reg(a[0].name())),
+};
+
+const DEC: Instruction = Instruction {
+    name: "dec",
+    operands: &[Update],
+    control: Control::Next,
+    exec: |m, a| m.update(a[0].name(), |v| v.checked_sub(1)),
+    compile: |c, a| Op::Dec(c.reg(a[0].name())),
+};
+
+const JNZ: Instruction = Instruction {
+    name: "jnz",
+    operands: &[Value, Value],
+    control: Control::Relative,
+    exec: |m, a| {
+        if m.value(&a[0]) == 0 {
+            return Some(Flow::Next);
+        }
+        // an offset big enough to overflow can only point past the last line
+        let target = (m.idx as i64)
+            .checked_add(m.value(&a[1]))
+            .map_or(Ok(usize::MAX), usize::try_from);
+        Some(Flow::Jump(target.ok()?))
+    },
+    compile: |c, a| c.jnz(&a[0], &a[1]),
+};
@@ -0,0 +1,173 @@
+//! Tokens of a single source line.
+//!
+//! Operands are identifiers (registers and labels), integers (`42`, `-7`, `0x1f`, `-0X10`) and
+//! quoted literals in either `'` or `"` quotes, with `\n`, `\t`, `\r`, `\0`, `\\`, `\'` and `\"`
+//! escapes. Commas and whitespace only separate tokens, a `;` outside of quotes starts a
+//! comment.
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token<'a> {
+    Ident(&'a str),
+    Int(i64),
+    /// A quoted literal with its escapes resolved
+    Quoted(String),
+    Colon,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LexError {
+    UnterminatedQuote,
+    UnknownEscape(char),
+    BadNumber,
+    UnexpectedChar(char),
+}
+
+struct Lexer<'a> {
+    line: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Consumes characters while `f` holds and returns the end of the consumed span
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) -> usize {
+        while self.chars.next_if(|(_, c)| f(*c)).is_some() {}
+        self.chars.peek().map_or(self.line.len(), |(i, _)| *i)
+    }
+
+    fn ident(&mut self, start: usize) -> Token<'a> {
+        let end = self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        Token::Ident(&self.line[start..end])
+    }
+
+    fn int(&mut self, start: usize) -> Result<Token<'a>, LexError> {
+        // the sign or the first digit
+        self.chars.next();
+        let end = self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        let text = &self.line[start..end];
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+        let parsed = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(&format!("{sign}{hex}"), 16),
+            None => format!("{sign}{digits}").parse(),
+        };
+        parsed.map(Token::Int).map_err(|_| LexError::BadNumber)
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<Token<'a>, LexError> {
+        let mut text = String::new();
+        loop {
+            match self.chars.next().ok_or(LexError::UnterminatedQuote)?.1 {
+                c if c == quote => return Ok(Token::Quoted(text)),
+                '\\' => text.push(
+                    match self.chars.next().ok_or(LexError::UnterminatedQuote)?.1 {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        c @ ('\\' | '\'' | '"') => c,
+                        c => return Err(LexError::UnknownEscape(c)),
+                    },
+                ),
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+/// Splits `line` into tokens and the text of its trailing comment, if any
+pub(crate) fn lex(line: &str) -> Result<(Vec<Token<'_>>, Option<&str>), LexError> {
+    let (spanned, comment) = lex_spanned(line)?;
+    Ok((
+        spanned.into_iter().map(|(token, _)| token).collect(),
+        comment,
+    ))
+}
+
+/// A token along with its source text
+pub(crate) type Spanned<'a> = (Token<'a>, &'a str);
+
+/// Same as [`lex`] but every token comes with its source text
+pub(crate) fn lex_spanned(line: &str) -> Result<(Vec<Spanned<'_>>, Option<&str>), LexError> {
+    let mut lexer = Lexer {
+        line,
+        chars: line.char_indices().peekable(),
+    };
+    let mut tokens = vec![];
+    while let Some(&(start, ch)) = lexer.chars.peek() {
+        let token = match ch {
+            ';' => return Ok((tokens, Some(&line[start + 1..]))),
+            ',' => {
+                lexer.chars.next();
+                continue;
+            }
+            c if c.is_whitespace() => {
+                lexer.chars.next();
+                continue;
+            }
+            ':' => {
+                lexer.chars.next();
+                Token::Colon
+            }
+            '\'' | '"' => {
+                lexer.chars.next();
+                lexer.quoted(ch)?
+            }
+            c if c.is_ascii_digit() || c == '-' => lexer.int(start)?,
+            c if c.is_ascii_alphabetic() || c == '_' => lexer.ident(start),
+            c => return Err(LexError::UnexpectedChar(c)),
+        };
+        let end = lexer.chars.peek().map_or(line.len(), |(i, _)| *i);
+        tokens.push((token, &line[start..end]));
+    }
+    Ok((tokens, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Token::*;
+
+    #[test]
+    fn operands() {
+        assert_eq!(
+            lex("mov count, -0x1F ; hex"),
+            Ok((vec![Ident("mov"), Ident("count"), Int(-31)], Some(" hex")))
+        );
+        assert_eq!(
+            lex("jnz a -1"),
+            Ok((vec![Ident("jnz"), Ident("a"), Int(-1)], None))
+        );
+        assert_eq!(lex("loop_2:"), Ok((vec![Ident("loop_2"), Colon], None)));
+    }
+
+    #[test]
+    fn quoted() {
+        assert_eq!(
+            lex(r#"msg 'it\'s; a, b', "\"q\"\n", x"#),
+            Ok((
+                vec![
+                    Ident("msg"),
+                    Quoted("it's; a, b".to_string()),
+                    Quoted("\"q\"\n".to_string()),
+                    Ident("x"),
+                ],
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(lex("msg 'oops"), Err(LexError::UnterminatedQuote));
+        assert_eq!(lex(r"msg '\q'"), Err(LexError::UnknownEscape('q')));
+        assert_eq!(lex("mov a, 12ab"), Err(LexError::BadNumber));
+        assert_eq!(lex("mov a, 0x"), Err(LexError::BadNumber));
+        assert_eq!(lex("mov a, 99999999999999999999"), Err(LexError::BadNumber));
+        assert_eq!(lex("mov a, $"), Err(LexError::UnexpectedChar('$')));
+    }
+}
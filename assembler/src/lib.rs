@@ -0,0 +1,134 @@
+//! Assembler interpreter engine shared by the assembler katas.
+//!
+//! A source is first [`parse`]d for a [`Dialect`] and then [`run`]. Each dialect has its own
+//! instruction table, so the katas only differ in which table they pick and how they read the
+//! [`Outcome`]. Loop-heavy programs can be [`compile`]d to bytecode and [`execute`]d instead of
+//! being [`run`] line by line. [`analyze`] reports likely mistakes without running anything,
+//! [`format`] lays a source out canonically.
+
+mod analysis;
+mod bytecode;
+mod format;
+mod instructions;
+mod lexer;
+mod machine;
+mod parse;
+
+pub use analysis::{analyze, Diagnostic};
+pub use bytecode::{compile, execute, Bytecode, CompileError};
+pub use format::{format, is_formatted};
+pub use machine::{run, Outcome};
+pub use parse::{parse, Program};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    /// `mov`, `inc`, `dec` and `jnz` with a relative offset. There are no labels or comments,
+    /// an unknown instruction aborts the program and running past the last line is a normal
+    /// termination.
+    Simple,
+    /// Labels, calls, comparisons, stack and I/O instructions, `msg` output. Unknown lines are
+    /// skipped and the program has to reach `end`, otherwise it produces nothing.
+    PartII,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_dialect() {
+        let source = "mov a 5\ninc a\ndec a\ndec a\njnz a -1\ninc a";
+        let outcome = run(&parse(source, Dialect::Simple), &[]).unwrap();
+        assert_eq!(outcome.registers, [("a".to_string(), 1)].into());
+        assert_eq!(outcome.output, None);
+    }
+
+    #[test]
+    fn simple_dialect_rejects_unknown_instructions() {
+        assert!(run(&parse("mov a 5\nmsg a", Dialect::Simple), &[]).is_none());
+    }
+
+    #[test]
+    fn simple_dialect_keeps_blank_lines() {
+        let program = parse("mov a 1\njnz a 2\n\nmov b 2", Dialect::Simple);
+        let expected = [("a".to_string(), 1), ("b".to_string(), 2)].into();
+        assert_eq!(run(&program, &[]).unwrap().registers, expected);
+        assert_eq!(
+            execute(&compile(&program).unwrap(), &[]).unwrap().registers,
+            expected
+        );
+        assert!(run(&parse("mov a 1\n\ninc a", Dialect::Simple), &[]).is_none());
+    }
+
+    #[test]
+    fn part_ii_dialect() {
+        let source =
+            "mov a, 5 ; five\ncall half\nmsg 'a = ', a\nend\n\nhalf:\n    div a, 2\n    ret\n";
+        let outcome = run(&parse(source, Dialect::PartII), &[]).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("a = 2"));
+        assert_eq!(outcome.registers["a"], 2);
+    }
+
+    #[test]
+    fn part_ii_requires_end() {
+        assert!(run(&parse("mov a, 5\nmsg a", Dialect::PartII), &[]).is_none());
+    }
+
+    #[test]
+    fn arithmetic_errors_abort() {
+        for source in [
+            "mov a, 7\nmod a, 0\nend",
+            "mov a, 7\ndiv a, 0\nend",
+            "mov a, -9223372036854775808\ndiv a, -1\nend",
+            "mov a, -9223372036854775808\nmod a, -1\nend",
+            "mov a, 9223372036854775807\nadd a, 1\nend",
+            "mov a, -9223372036854775808\nsub a, 1\nend",
+            "mov a, 4611686018427387904\nmul a, 2\nend",
+            "mov a, 9223372036854775807\ninc a\nend",
+            "mov a, -9223372036854775808\ndec a\nend",
+        ] {
+            let program = parse(source, Dialect::PartII);
+            assert_eq!(run(&program, &[]), None, "{source}");
+            assert_eq!(execute(&compile(&program).unwrap(), &[]), None, "{source}");
+        }
+        for source in [
+            "mov a 9223372036854775807\ninc a",
+            "mov a -9223372036854775808\ndec a",
+        ] {
+            let program = parse(source, Dialect::Simple);
+            assert_eq!(run(&program, &[]), None, "{source}");
+            assert_eq!(execute(&compile(&program).unwrap(), &[]), None, "{source}");
+        }
+    }
+
+    #[test]
+    fn overflowing_jump_runs_past_the_end() {
+        let source = "mov a 1\njnz a 9223372036854775807\nmov b 2";
+        let program = parse(source, Dialect::Simple);
+        let outcome = run(&program, &[]).unwrap();
+        assert_eq!(outcome.registers, [("a".to_string(), 1)].into());
+        assert_eq!(execute(&compile(&program).unwrap(), &[]), Some(outcome));
+    }
+
+    #[test]
+    fn identifier_registers_and_literals() {
+        let source = r#"
+mov   count, 0x10      ; hex
+mov   step, -0X2
+mov   letter, 'A'
+loop:
+    add   count, step
+    inc   letter
+    cmp   count, 10
+    jg    loop
+msg   "count = ", count, ', letter = ', letter, '; it\'s "quoted", ok\n'
+end
+"#;
+        let program = parse(source, Dialect::PartII);
+        let expected = "count = 10, letter = 68; it's \"quoted\", ok\n";
+        let outcome = run(&program, &[]).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some(expected));
+        assert_eq!(outcome.registers["count"], 10);
+        assert_eq!(execute(&compile(&program).unwrap(), &[]), Some(outcome));
+    }
+}
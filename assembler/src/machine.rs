@@ -0,0 +1,115 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use crate::instructions::BinOp;
+use crate::parse::{Arg, Program};
+use crate::Dialect;
+
+pub(crate) enum Flow {
+    Next,
+    Jump(usize),
+    End,
+}
+
+pub(crate) struct Machine<'a> {
+    labels: &'a HashMap<&'a str, usize>,
+    pub registers: HashMap<&'a str, i64>,
+    pub calls: Vec<usize>,
+    pub stack: Vec<i64>,
+    pub input: VecDeque<i64>,
+    output: Option<String>,
+    pub last_cmp: Option<Ordering>,
+    pub idx: usize,
+}
+
+impl<'a> Machine<'a> {
+    pub fn value(&self, x: &Arg) -> i64 {
+        match x {
+            Arg::Reg(name) => *self
+                .registers
+                .get(name)
+                .expect("register is empty but is requested"),
+            Arg::Imm(v) => *v,
+            arg => panic!("{arg:?} is not a value"),
+        }
+    }
+
+    pub fn label(&self, lbl: &str) -> Option<Flow> {
+        Some(Flow::Jump(*self.labels.get(lbl)?))
+    }
+
+    /// Replaces the value of register `x` by `f` of it, `None` if `f` fails
+    pub fn update(&mut self, x: &'a str, f: impl FnOnce(i64) -> Option<i64>) -> Option<Flow> {
+        if let Some(v) = self.registers.get_mut(x) {
+            *v = f(*v)?;
+        }
+        Some(Flow::Next)
+    }
+
+    pub fn binary(&mut self, args: &[Arg<'a>], op: BinOp) -> Option<Flow> {
+        let val = self.value(&args[1]);
+        self.update(args[0].name(), |v| op.apply(v, val))
+    }
+
+    pub fn jump_if(&mut self, lbl: &str, cond: fn(Ordering) -> bool) -> Option<Flow> {
+        if cond(self.last_cmp.take()?) {
+            self.label(lbl)
+        } else {
+            Some(Flow::Next)
+        }
+    }
+
+    pub fn print(&mut self, part: &str) {
+        self.output.get_or_insert_with(String::new).push_str(part);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub registers: HashMap<String, i64>,
+    /// Everything printed by `msg` and `out`, `None` if nothing was
+    pub output: Option<String>,
+}
+
+/// Executes `program`; `in` instructions read their values from `input`, in order.
+///
+/// Returns `None` if the program is aborted: a jump to a missing label, `ret` or `pop` on an
+/// empty stack, an exhausted input, an arithmetic overflow or a zero divisor, or anything else
+/// its dialect treats as an error.
+pub fn run(program: &Program, input: &[i64]) -> Option<Outcome> {
+    let mut machine = Machine {
+        labels: &program.labels,
+        registers: HashMap::new(),
+        calls: vec![],
+        stack: vec![],
+        input: input.iter().copied().collect(),
+        output: None,
+        last_cmp: None,
+        idx: 0,
+    };
+    loop {
+        let flow = match (
+            program.lines.get(machine.idx).map(|l| &l.inst),
+            program.dialect,
+        ) {
+            (Some(Some((inst, args))), _) => (inst.exec)(&mut machine, args)?,
+            (Some(None), Dialect::PartII) => Flow::Next,
+            (Some(None), Dialect::Simple) => return None,
+            (None, Dialect::Simple) => break,
+            (None, Dialect::PartII) => return None,
+        };
+        match flow {
+            Flow::Next => machine.idx += 1,
+            Flow::Jump(idx) => machine.idx = idx,
+            Flow::End => break,
+        }
+    }
+    Some(Outcome {
+        registers: machine
+            .registers
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+        output: machine.output,
+    })
+}
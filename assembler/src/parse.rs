@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::instructions::{Instruction, Operand};
+use crate::lexer::{lex, Token};
+use crate::Dialect;
+
+/// A decoded operand
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Arg<'a> {
+    Reg(&'a str),
+    Imm(i64),
+    Label(&'a str),
+    /// A string literal, only allowed in an [`Operand::List`]
+    Text(String),
+}
+
+impl<'a> Arg<'a> {
+    /// A register, an integer or a single quoted character
+    fn value(token: Token<'a>) -> Option<Self> {
+        match token {
+            Token::Ident(name) => Some(Arg::Reg(name)),
+            Token::Int(v) => Some(Arg::Imm(v)),
+            Token::Quoted(text) => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Arg::Imm(c as i64)),
+                    _ => None,
+                }
+            }
+            Token::Colon => None,
+        }
+    }
+
+    /// Name of a register or label operand
+    pub fn name(&self) -> &'a str {
+        match self {
+            Arg::Reg(name) | Arg::Label(name) => name,
+            arg => panic!("{arg:?} is neither a register nor a label"),
+        }
+    }
+}
+
+/// An instruction along with its operands
+pub(crate) type Decoded<'a> = (&'static Instruction, Vec<Arg<'a>>);
+
+pub(crate) struct Line<'a> {
+    /// 1-based line number in the source
+    pub number: usize,
+    pub label: Option<&'a str>,
+    /// `None` for labels and for anything the dialect doesn't recognize
+    pub inst: Option<Decoded<'a>>,
+}
+
+pub struct Program<'a> {
+    pub(crate) dialect: Dialect,
+    pub(crate) lines: Vec<Line<'a>>,
+    /// Index into `lines` of every label; if a label is defined twice, the last one wins
+    pub(crate) labels: HashMap<&'a str, usize>,
+}
+
+fn decode(dialect: Dialect, tokens: Vec<Token<'_>>) -> Option<Decoded<'_>> {
+    let mut tokens = tokens.into_iter();
+    let Some(Token::Ident(name)) = tokens.next() else {
+        return None;
+    };
+    let inst = dialect.instructions().iter().find(|i| i.name == name)?;
+    let mut args = Vec::with_capacity(inst.operands.len());
+    for op in inst.operands {
+        match (op, tokens.next()) {
+            (Operand::List, first) => {
+                for token in first.into_iter().chain(tokens.by_ref()) {
+                    args.push(match token {
+                        Token::Quoted(text) => Arg::Text(text),
+                        token => Arg::value(token)?,
+                    });
+                }
+            }
+            (Operand::Register | Operand::Update, Some(Token::Ident(name))) => {
+                args.push(Arg::Reg(name))
+            }
+            (Operand::Label, Some(Token::Ident(name))) => args.push(Arg::Label(name)),
+            (Operand::Value, Some(token)) => args.push(Arg::value(token)?),
+            _ => return None,
+        }
+    }
+    tokens.next().is_none().then_some((inst, args))
+}
+
+/// Splits `source` into instructions, dropping `;` comments and collecting `label:` lines.
+/// Blank lines are dropped too, except in [`Dialect::Simple`] where `jnz` counts them and they
+/// stay as unknown lines. Lines are never rejected here, see [`Dialect`] for how they're treated
+/// at runtime.
+pub fn parse(source: &str, dialect: Dialect) -> Program<'_> {
+    let mut labels = HashMap::new();
+    let mut lines = vec![];
+    for (number, line) in source.lines().enumerate() {
+        // a line that doesn't lex is kept as an unknown one
+        let tokens = match lex(line) {
+            Ok((tokens, _)) if tokens.is_empty() && dialect == Dialect::PartII => continue,
+            Ok((tokens, _)) => tokens,
+            Err(_) => vec![],
+        };
+        let label = match tokens[..] {
+            [Token::Ident(label), Token::Colon] => {
+                labels.insert(label, lines.len());
+                Some(label)
+            }
+            _ => None,
+        };
+        lines.push(Line {
+            number: number + 1,
+            label,
+            inst: decode(dialect, tokens),
+        });
+    }
+    Program {
+        dialect,
+        lines,
+        labels,
+    }
+}
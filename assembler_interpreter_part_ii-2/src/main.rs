@@ -1,178 +1,15 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
-
-#[inline]
-fn is_register(x: &str) -> bool {
-    x.len() == 1 && ('a'..='z').contains(&x.chars().next().unwrap())
-}
-
-#[inline]
-fn resolve(x: &str, registers: &HashMap<&str, i64>) -> i64 {
-    if is_register(x) {
-        registers
-            .get(x)
-            .expect("register is empty but is requested")
-            .clone()
-    } else {
-        x.parse::<i64>()
-            .expect("value must be either a register or a number")
-    }
-}
-
-#[inline]
-fn arithmetics(op: &str, lhs: i64, rhs: i64) -> i64 {
-    match op {
-        "add" => lhs + rhs,
-        "sub" => lhs - rhs,
-        "mul" => lhs * rhs,
-        "div" => lhs / rhs,
-        _ => panic!("invalid arithmetics op {op}"),
-    }
-}
-
-#[inline]
-fn need_jump(op: &str, ord: Ordering) -> bool {
-    use Ordering::*;
-    match op {
-        "jne" => ord != Equal,
-        "je" => ord == Equal,
-        "jge" => ord == Equal || ord == Greater,
-        "jg" => ord == Greater,
-        "jle" => ord == Equal || ord == Less,
-        "jl" => ord == Less,
-        _ => panic!("invalid jump op {op}"),
-    }
-}
-
-enum ArgKind {
-    Text(String),
-    Var(char),
-}
+use assembler::{parse, run, Dialect};
 
 pub struct AssemblerInterpreter {}
 
 impl AssemblerInterpreter {
     pub fn interpret(input: &str) -> Option<String> {
-        let program = input
-            .lines()
-            .filter_map(|l| match l.trim() {
-                x if x.len() > 0 && !x.starts_with(';') => match x.rsplit_once(";") {
-                    None => Some(x),
-                    Some((left, _)) => Some(left),
-                },
-                _ => None,
-            })
-            .map(|l| {
-                if l.starts_with("msg") {
-                    l.to_string()
-                } else {
-                    l.replace(',', "")
-                }
-            })
-            .collect::<Vec<_>>();
-        let labels = program
-            .iter()
-            .enumerate()
-            .filter_map(|(i, l)| match l.split_once(':') {
-                Some((v, "")) if v.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
-                    Some((v, i))
-                }
-                _ => None,
-            })
-            .collect::<HashMap<&str, usize>>();
-        let mut registers: HashMap<&str, i64> = HashMap::new();
-        let mut stack = vec![];
-        let mut buf = Vec::with_capacity(3);
-        let mut output: Option<String> = None;
-        let mut last_cmp = None;
-        let mut idx: usize = 0;
-        loop {
-            let inst = program.get(idx)?;
-            buf.clear();
-            buf.extend(inst.split_whitespace().take(3));
-            match buf[..] {
-                ["mov", x, y] if is_register(x) => {
-                    registers.insert(x, resolve(y, &registers));
-                    idx += 1;
-                }
-                ["inc", x] if is_register(x) => {
-                    registers.entry(x).and_modify(|v| *v += 1);
-                    idx += 1;
-                }
-                ["dec", x] if is_register(x) => {
-                    registers.entry(x).and_modify(|v| *v -= 1);
-                    idx += 1;
-                }
-                [op @ ("add" | "sub" | "mul" | "div"), x, y] if is_register(x) => {
-                    let val = resolve(y, &registers);
-                    registers
-                        .entry(x)
-                        .and_modify(|v| *v = arithmetics(op, *v, val));
-                    idx += 1;
-                }
-                ["jmp", lbl] => idx = *labels.get(lbl)?,
-                ["cmp", x, y] => {
-                    last_cmp = Some(resolve(x, &registers).cmp(&resolve(y, &registers)));
-                    idx += 1;
-                }
-                [op @ ("jne" | "je" | "jge" | "jg" | "jle" | "jl"), lbl] => {
-                    if need_jump(op, last_cmp.take()?) {
-                        idx = *labels.get(lbl)?;
-                    } else {
-                        idx += 1;
-                    }
-                }
-                ["call", lbl] => {
-                    stack.push(idx + 1);
-                    idx = *labels.get(lbl)?;
-                }
-                ["ret"] => {
-                    idx = stack.pop()?;
-                }
-                ["msg", ..] => {
-                    let (_, rest) = inst.split_once("msg").unwrap();
-                    let mut args = vec![];
-                    let mut msg_buf = None;
-                    for ch in rest.chars() {
-                        // todo: make an iterator
-                        match (ch, msg_buf.take()) {
-                            ('\'', None) => msg_buf = Some(String::new()),
-                            ('\'', Some(v)) => {
-                                args.push(ArgKind::Text(v));
-                                msg_buf = None;
-                            }
-                            (',' | ' ', None) => {}
-                            (c, None) => args.push(ArgKind::Var(c)),
-                            (c, Some(mut v)) => {
-                                v.push(c);
-                                msg_buf = Some(v);
-                            }
-                        }
-                    }
+        Self::interpret_with_input(input, &[])
+    }
 
-                    let out_part = args
-                        .into_iter()
-                        .map(|arg| match arg {
-                            ArgKind::Text(txt) => txt,
-                            ArgKind::Var(c) => resolve(&c.to_string(), &registers).to_string(),
-                        })
-                        .collect::<String>();
-                    output = match output.take() {
-                        None => Some(out_part),
-                        Some(mut v) => {
-                            v.push_str(&out_part);
-                            Some(v)
-                        }
-                    };
-                    idx += 1;
-                }
-                ["end"] => break,
-                _ => {
-                    idx += 1;
-                }
-            }
-        }
-        output
+    /// Same as `interpret` but `in` instructions read their values from `queue`, in order
+    pub fn interpret_with_input(input: &str, queue: &[i64]) -> Option<String> {
+        run(&parse(input, Dialect::PartII), queue)?.output
     }
 }
 
@@ -202,8 +39,75 @@ pub mod tests {
         ];
 
         for (prg, exp) in simple_programs.iter().zip(expected) {
-            let actual = AssemblerInterpreter::interpret(*prg);
+            let actual = AssemblerInterpreter::interpret(prg);
             assert_eq!(actual, *exp);
         }
     }
+
+    #[test]
+    fn stack_ops() {
+        let program = "mov a, 1\npush a\npush 2\npop b\npop c\nmsg b, c\nend\n";
+        assert_eq!(
+            AssemblerInterpreter::interpret(program),
+            Some(String::from("21"))
+        );
+        assert_eq!(AssemblerInterpreter::interpret("pop a\nend\n"), None);
+    }
+
+    #[test]
+    fn modulo() {
+        let program = "mov a, 17\nmod a, 5\nmov b, -7\nmod b, 3\nmsg a, ' ', b\nend\n";
+        assert_eq!(
+            AssemblerInterpreter::interpret(program),
+            Some(String::from("2 -1"))
+        );
+    }
+
+    #[test]
+    fn bitwise_ops() {
+        let program = "\nmov a, 12\nand a, 10\nmov b, 12\nor  b, 3\nmov c, 6\nxor c, 3\nmov d, 0\nnot d\nmov e, 3\nshl e, 4\nmov f, -16\nshr f, 2\nmsg a, ' ', b, ' ', c, ' ', d, ' ', e, ' ', f\nend\n";
+        assert_eq!(
+            AssemblerInterpreter::interpret(program),
+            Some(String::from("8 15 5 -1 48 -4"))
+        );
+    }
+
+    #[test]
+    fn input_output() {
+        let program = "\nin  a\nin  b\nadd a, b\nout a\nmsg ' '\nout 7\nend\n";
+        assert_eq!(
+            AssemblerInterpreter::interpret_with_input(program, &[40, 2]),
+            Some(String::from("42 7"))
+        );
+        assert_eq!(
+            AssemblerInterpreter::interpret_with_input(program, &[40]),
+            None
+        );
+    }
+
+    #[test]
+    fn arithmetic_errors_abort() {
+        for program in [
+            "mov a, 7\nmod a, 0\nend",
+            "mov a, 7\ndiv a, 0\nend",
+            "mov a, -9223372036854775808\ndiv a, -1\nend",
+            "mov a, -9223372036854775808\nmod a, -1\nend",
+            "mov a, 9223372036854775807\nadd a, 1\nend",
+            "mov a, -9223372036854775808\nsub a, 1\nend",
+            "mov a, 4611686018427387904\nmul a, 2\nend",
+            "mov a, 9223372036854775807\ninc a\nend",
+            "mov a, -9223372036854775808\ndec a\nend",
+        ] {
+            assert_eq!(AssemblerInterpreter::interpret(program), None, "{program}");
+        }
+    }
+
+    #[test]
+    fn nop() {
+        let program = "mov a, 1\nnop\nnop\nout a\nend\n";
+        assert_eq!(
+            AssemblerInterpreter::interpret(program),
+            Some(String::from("1"))
+        );
+    }
 }
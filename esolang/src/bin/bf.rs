@@ -0,0 +1,95 @@
+//! `bf [--boolfuck] [--eof unchanged|zero|minus-one|error] [--max-steps N] [--timeout SECS] FILE`
+//!
+//! Runs a brainfuck program, or a boolfuck one with `--boolfuck`, against stdin and stdout.
+//! Output is shown as soon as the program asks for input, so interactive programs work.
+//! `--eof` picks what a brainfuck `,` does at the end of input, an error by default.
+//! `--max-steps` and `--timeout` stop programs that run for too long, with exit status 1 like
+//! any other error.
+
+use std::io::{self, BufWriter};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use esolang::boolfuck;
+use esolang::brainfuck::{self, BfConfig, Eof};
+use esolang::limits::Limits;
+
+const USAGE: &str = "usage: bf [--boolfuck] [--eof unchanged|zero|minus-one|error] \
+                     [--max-steps N] [--timeout SECS] FILE";
+
+fn eof(mode: &str) -> Option<Eof> {
+    match mode {
+        "unchanged" => Some(Eof::Unchanged),
+        "zero" => Some(Eof::Zero),
+        "minus-one" => Some(Eof::MinusOne),
+        "error" => Some(Eof::Error),
+        _ => None,
+    }
+}
+
+fn main() -> ExitCode {
+    let mut boolfuck = false;
+    let mut config = BfConfig::default();
+    let mut limits = Limits::default();
+    let mut timeout = None;
+    let mut files = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--boolfuck" => {
+                boolfuck = true;
+                Some(())
+            }
+            "--eof" => args
+                .next()
+                .and_then(|mode| eof(&mode))
+                .map(|eof| config.eof = eof),
+            "--max-steps" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .map(|n| limits.max_steps = Some(n)),
+            "--timeout" => args
+                .next()
+                .and_then(|secs| Duration::try_from_secs_f64(secs.parse().ok()?).ok())
+                .map(|secs| timeout = Some(secs)),
+            _ => {
+                files.push(arg);
+                Some(())
+            }
+        };
+        if parsed.is_none() {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    }
+    let [file] = &files[..] else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let code = match fs::read_to_string(file) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{file}: {e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    limits.deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let input = io::stdin().lock();
+    let output = BufWriter::new(io::stdout().lock());
+    let (steps, result) = if boolfuck {
+        let outcome = boolfuck::run_io(&code, input, output, &limits);
+        (outcome.steps, outcome.result.map_err(|e| e.to_string()))
+    } else {
+        let outcome = brainfuck::run_io(&code, input, output, &config, &limits);
+        (outcome.steps, outcome.result.map_err(|e| e.to_string()))
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{file}:{e} after {steps} steps");
+            ExitCode::FAILURE
+        }
+    }
+}
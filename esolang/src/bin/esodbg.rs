@@ -0,0 +1,71 @@
+//! `esodbg [--boolfuck] FILE [INPUT]`
+//!
+//! Steps through a brainfuck program, or a boolfuck one with `--boolfuck`, reading commands
+//! from stdin and showing the program state after each of them:
+//!
+//! - `s` or an empty line executes one command
+//! - `c` continues to the next `#` breakpoint
+//! - a number `n` executes that many commands
+//! - `q` quits
+
+use std::io::{self, BufRead, IsTerminal};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use esolang::debugger::{Debugger, Language, State};
+
+fn main() -> ExitCode {
+    let mut language = Language::Brainfuck;
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--boolfuck" => language = Language::Boolfuck,
+            _ => args.push(arg),
+        }
+    }
+    let (file, input) = match &args[..] {
+        [file] => (file, String::new()),
+        [file, input] => (file, input.clone()),
+        _ => {
+            eprintln!("usage: esodbg [--boolfuck] FILE [INPUT]");
+            return ExitCode::from(2);
+        }
+    };
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{file}: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut debugger = match Debugger::new(language, &source, input.into_bytes()) {
+        Ok(debugger) => debugger,
+        Err(e) => {
+            eprintln!("{file}:{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let color = io::stdout().is_terminal();
+    print!("{}", debugger.render(color));
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        match line.trim() {
+            "" | "s" => debugger.step(),
+            "c" => debugger.run(None),
+            "q" => break,
+            n => match n.parse() {
+                Ok(n) => debugger.run(Some(n)),
+                Err(_) => {
+                    eprintln!("commands: s, c, <steps>, q");
+                    continue;
+                }
+            },
+        };
+        print!("{}", debugger.render(color));
+        if matches!(debugger.state(), State::Halted | State::InputExhausted) {
+            break;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,352 @@
+//! Boolfuck: brainfuck over single bits, with `;` writing the current bit. I/O is little-endian
+//! and reading past the end of the input yields zero bits.
+//!
+//! Programs run on a [`BitTape`] after folding runs of moves and flips, which are most of what
+//! boolfuck code is made of.
+
+use std::io::{self, BufRead, Write};
+use std::{error, fmt};
+
+use crate::brackets::{match_brackets, BracketError};
+use crate::error::{Located, Position};
+use crate::io::{BitReader, BitWriter};
+use crate::limits::{Budget, LimitExceeded, Limits, Outcome};
+use crate::tape::BitTape;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoolfuckError {
+    Brackets(BracketError),
+    /// Reading the input or writing the output failed
+    Io(io::ErrorKind),
+    LimitExceeded(LimitExceeded),
+}
+
+impl fmt::Display for BoolfuckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoolfuckError::Brackets(err) => err.fmt(f),
+            BoolfuckError::Io(kind) => write!(f, "i/o error: {kind}"),
+            BoolfuckError::LimitExceeded(limit) => limit.fmt(f),
+        }
+    }
+}
+
+impl error::Error for BoolfuckError {}
+
+impl From<io::Error> for BoolfuckError {
+    fn from(err: io::Error) -> Self {
+        BoolfuckError::Io(err.kind())
+    }
+}
+
+impl From<LimitExceeded> for BoolfuckError {
+    fn from(limit: LimitExceeded) -> Self {
+        BoolfuckError::LimitExceeded(limit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Flip,
+    Move(isize),
+    Out,
+    In,
+    /// Jumps past the matching `Close` if the current bit is zero
+    Open(usize),
+    /// Jumps back past the matching `Open` if the current bit is one
+    Close(usize),
+}
+
+struct Program {
+    ops: Vec<Op>,
+    /// Where in the source every op starts
+    positions: Vec<Position>,
+    /// Just past the end of the source, where the final flush fails
+    end: Position,
+}
+
+impl Program {
+    fn push(&mut self, op: Op, position: Position) {
+        self.ops.push(op);
+        self.positions.push(position);
+    }
+
+    fn pop(&mut self) {
+        self.ops.pop();
+        self.positions.pop();
+    }
+}
+
+/// Folds runs of `>`/`<` into one move and drops pairs of `+`, which cancel out
+fn compile(code: &str) -> Result<Program, Located<BoolfuckError>> {
+    match_brackets(&code.chars().collect::<Vec<_>>()).map_err(|error| Located {
+        error: BoolfuckError::Brackets(error),
+        position: Position::of(code, error.index()),
+    })?;
+    let mut program = Program {
+        ops: vec![],
+        positions: vec![],
+        end: Position::START,
+    };
+    let mut opens = vec![];
+    let mut position = Position::START;
+    for ch in code.chars() {
+        let at = position;
+        position = position.after(ch);
+        match (ch, program.ops.last_mut()) {
+            ('+', Some(Op::Flip)) => program.pop(),
+            ('+', _) => program.push(Op::Flip, at),
+            ('>' | '<', last) => {
+                let n = if ch == '>' { 1 } else { -1 };
+                match last {
+                    Some(Op::Move(m)) => {
+                        *m += n;
+                        if *m == 0 {
+                            program.pop();
+                        }
+                    }
+                    _ => program.push(Op::Move(n), at),
+                }
+            }
+            (';', _) => program.push(Op::Out, at),
+            (',', _) => program.push(Op::In, at),
+            ('[', _) => {
+                opens.push(program.ops.len());
+                program.push(Op::Open(0), at);
+            }
+            (']', _) => {
+                let open = opens.pop().unwrap();
+                program.ops[open] = Op::Open(program.ops.len());
+                program.push(Op::Close(open), at);
+            }
+            _ => {}
+        }
+    }
+    program.end = position;
+    Ok(program)
+}
+
+/// Runs `code`; output with a partial last byte is padded with zero bits. Characters other
+/// than the commands are ignored.
+pub fn try_run(code: &str, input: Vec<u8>) -> Result<Vec<u8>, Located<BoolfuckError>> {
+    run_io(code, &input[..], vec![], &Limits::default()).into_result()
+}
+
+/// Runs `code` within `limits`, reading `input` as it goes. Output bits are written to
+/// `output` a byte at a time, and the whole bytes so far are flushed before every read. A
+/// program that fails doesn't get its partial last byte written.
+///
+/// Steps are commands with runs of moves and flips folded, so `>>>` is a single step.
+pub fn run_io<W: Write>(
+    code: &str,
+    input: impl BufRead,
+    output: W,
+    limits: &Limits,
+) -> Outcome<BoolfuckError, W> {
+    let program = match compile(code) {
+        Ok(program) => program,
+        Err(err) => {
+            return Outcome {
+                output,
+                steps: 0,
+                result: Err(err),
+            }
+        }
+    };
+    let mut pc = 0;
+    let mut budget = Budget::new(limits);
+    let mut output = BitWriter::new(output);
+    let result = execute(&program.ops, input, &mut output, &mut pc, &mut budget)
+        .and_then(|()| Ok(output.finish()?));
+    Outcome {
+        output: output.into_inner(),
+        steps: budget.steps,
+        result: result.map_err(|error| Located {
+            error,
+            position: program.positions.get(pc).copied().unwrap_or(program.end),
+        }),
+    }
+}
+
+/// Runs `ops` leaving `pc` at the failing op, if any
+fn execute(
+    ops: &[Op],
+    input: impl BufRead,
+    output: &mut BitWriter<impl Write>,
+    pc: &mut usize,
+    budget: &mut Budget,
+) -> Result<(), BoolfuckError> {
+    let mut input = BitReader::new(input);
+    let mut tape = BitTape::default();
+    while *pc < ops.len() {
+        budget.step()?;
+        match ops[*pc] {
+            Op::Flip => tape.flip(),
+            Op::Move(n) => tape.shift(n),
+            Op::Out => output.push(tape.get())?,
+            Op::In => {
+                output.flush()?;
+                tape.set(input.next().transpose()?.unwrap_or(false));
+            }
+            Op::Open(close) if !tape.get() => *pc = close,
+            Op::Close(open) if tape.get() => *pc = open,
+            _ => {}
+        }
+        *pc += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Read};
+    use std::time::Instant;
+
+    use super::*;
+    use crate::tape::{DenseTape, Tape};
+
+    const MULTIPLIER: &str = ">,>,>,>,>,>,>,>,>>,>,>,>,>,>,>,>,<<<<<<<<+<<<<<<<<+[>+]<[<]>>>>>>>>>[+<<<<<<<<[>]+<[+<]>>>>>>>>>>>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+<<<<<<<<[>]+<[+<]>>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+]>[>]+<[+<]>>>>>>>>>[+]>[>]+<[+<]>>>>>>>>>[+]<<<<<<<<<<<<<<<<<<+<<<<<<<<+[>+]<[<]>>>>>>>>>]<[+<]>>>>>>>>>>>>>>>>>>>>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+<<<<<<<<[>]+<[+<]>>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+]<<<<<<<<<<<<<<<<<<<<<<<<<<[>]+<[+<]>>>>>>>>>[+]>>>>>>>>>>>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>]<[+<]<<<<<<<<<<<<<<<<<<+<<<<<<<<+[>+]<[<]>>>>>>>>>[+]+<<<<<<<<+[>+]<[<]>>>>>>>>>]<[+<]>>>>>>>>>>>>>>>>>>>;>;>;>;>;>;>;>;<<<<<<<<";
+
+    /// Runs `code` one character at a time on a tape of `bool`s
+    fn reference(code: &str, input: Vec<u8>) -> Vec<u8> {
+        let instructions: Vec<_> = code.chars().collect();
+        let jumps = match_brackets(&instructions).unwrap();
+        let mut input = BitReader::new(&input[..]).map(Result::unwrap);
+        let mut output = BitWriter::default();
+        let mut tape: DenseTape<bool> = DenseTape::default();
+        let mut pc = 0;
+        while pc < instructions.len() {
+            match instructions[pc] {
+                '>' => tape.shift(1).unwrap(),
+                '<' => tape.shift(-1).unwrap(),
+                '+' => {
+                    let bit = tape.current();
+                    *bit = !*bit;
+                }
+                ';' => output.push(tape.get()).unwrap(),
+                ',' => tape.set(input.next().unwrap_or(false)),
+                '[' if !tape.get() => pc = jumps[pc],
+                ']' if tape.get() => pc = jumps[pc],
+                _ => {}
+            }
+            pc += 1;
+        }
+        output.finish().unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn flips_and_reads() {
+        assert_eq!(try_run("+;;+;", vec![]), Ok(vec![0b011]));
+        assert_eq!(try_run(",;,;+;", vec![0b10]), Ok(vec![0b010]));
+        // `,` overwrites the bit instead of or-ing into it
+        assert_eq!(try_run("+,;", vec![0]), Ok(vec![0]));
+    }
+
+    #[test]
+    fn unmatched_brackets() {
+        assert_eq!(
+            try_run("flip +\nloop [;", vec![]),
+            Err(Located {
+                error: BoolfuckError::Brackets(BracketError::UnmatchedOpen(12)),
+                position: Position { line: 2, column: 6 },
+            })
+        );
+    }
+
+    #[test]
+    fn folds_runs() {
+        assert_eq!(
+            compile("++>>><<+[<<<++>];").unwrap().ops,
+            [
+                Op::Move(1),
+                Op::Flip,
+                Op::Open(4),
+                Op::Move(-2),
+                Op::Close(2),
+                Op::Out
+            ]
+        );
+    }
+
+    #[test]
+    fn multiplier() {
+        for (a, b) in [(8, 9), (0, 200), (15, 17), (255, 255)] {
+            let expected = reference(MULTIPLIER, vec![a, b]);
+            assert_eq!(expected, [a.wrapping_mul(b)]);
+            assert_eq!(try_run(MULTIPLIER, vec![a, b]), Ok(expected));
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_against_reference() {
+        let start = Instant::now();
+        for a in 0..=255 {
+            reference(MULTIPLIER, vec![a, 255]);
+        }
+        let interpreted = start.elapsed();
+        let start = Instant::now();
+        for a in 0..=255 {
+            try_run(MULTIPLIER, vec![a, 255]).unwrap();
+        }
+        let compiled = start.elapsed();
+        println!(
+            "multiplier: reference {interpreted:?}, packed {compiled:?} ({:.1}x)",
+            interpreted.as_secs_f64() / compiled.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn streams_whole_bytes() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            }
+        }
+
+        // eleven bits are written before the read fails, only the first eight make it out
+        let mut output = vec![];
+        let outcome = run_io(
+            "+;;;;;;;;\n;;; ,",
+            BufReader::new(Broken),
+            &mut output,
+            &Limits::default(),
+        );
+        let err = outcome.result.unwrap_err();
+        assert_eq!(output, [0xff]);
+        assert_eq!(
+            err,
+            Located {
+                error: BoolfuckError::Io(io::ErrorKind::UnexpectedEof),
+                position: Position { line: 2, column: 5 },
+            }
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let limits = Limits {
+            max_steps: Some(50),
+            ..Limits::default()
+        };
+        // eight bits make it out before the loop, the ninth is dropped with the partial byte
+        let outcome = run_io("+;>>;;;;;;+;;[]", &[][..], vec![], &limits);
+        assert_eq!(outcome.output, [0b1000_0001]);
+        assert_eq!(outcome.steps, 50);
+        assert_eq!(
+            outcome.result,
+            Err(Located {
+                error: BoolfuckError::LimitExceeded(LimitExceeded::Steps),
+                position: Position {
+                    line: 1,
+                    column: 15
+                },
+            })
+        );
+        assert_eq!(run_io("+>>+;", &[][..], vec![], &limits).steps, 4);
+    }
+}
@@ -0,0 +1,75 @@
+//! Pairing of `[` and `]` in a program.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BracketError {
+    /// A `[` at this instruction index is never closed
+    UnmatchedOpen(usize),
+    /// A `]` at this instruction index has nothing to close
+    UnmatchedClose(usize),
+}
+
+impl BracketError {
+    /// Index of the offending bracket
+    pub fn index(self) -> usize {
+        match self {
+            BracketError::UnmatchedOpen(at) | BracketError::UnmatchedClose(at) => at,
+        }
+    }
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BracketError::UnmatchedOpen(_) => f.write_str("unmatched `[`"),
+            BracketError::UnmatchedClose(_) => f.write_str("unmatched `]`"),
+        }
+    }
+}
+
+impl std::error::Error for BracketError {}
+
+/// For every instruction, the index of the matching bracket if it is one. Anything else maps
+/// to itself.
+pub fn match_brackets(instructions: &[char]) -> Result<Vec<usize>, BracketError> {
+    let mut jumps: Vec<usize> = (0..instructions.len()).collect();
+    let mut opens = vec![];
+    for (i, ch) in instructions.iter().enumerate() {
+        match ch {
+            '[' => opens.push(i),
+            ']' => {
+                let open = opens.pop().ok_or(BracketError::UnmatchedClose(i))?;
+                jumps[open] = i;
+                jumps[i] = open;
+            }
+            _ => {}
+        }
+    }
+    match opens.pop() {
+        Some(open) => Err(BracketError::UnmatchedOpen(open)),
+        None => Ok(jumps),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jumps(code: &str) -> Result<Vec<usize>, BracketError> {
+        match_brackets(&code.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(jumps("+[>[-]<]."), Ok(vec![0, 7, 2, 5, 4, 3, 6, 1, 8]));
+        assert_eq!(jumps(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn unmatched() {
+        assert_eq!(jumps("[[]"), Err(BracketError::UnmatchedOpen(0)));
+        assert_eq!(jumps("[]]["), Err(BracketError::UnmatchedClose(2)));
+        assert_eq!(jumps("+["), Err(BracketError::UnmatchedOpen(1)));
+    }
+}
@@ -0,0 +1,228 @@
+//! Brainfuck with configurable conventions, compiled to an optimizing IR before it runs.
+//! Characters other than the eight commands are comments. Programs can also be [`transpile`]d
+//! to Rust or C, and written from higher level operations with a [`Generator`].
+
+mod config;
+mod generator;
+mod ir;
+mod transpile;
+
+use std::io::{BufRead, Write};
+
+use crate::error::Located;
+use crate::limits::{Limits, Outcome};
+pub use crate::tape::TapeModel;
+pub use config::{BfConfig, BfError, CellSize, Eof, Overflow};
+pub use generator::Generator;
+pub use transpile::{transpile, Target};
+
+/// Runs `code` with the conventions in `config`
+pub fn try_run(code: &str, input: Vec<u8>, config: &BfConfig) -> Result<Vec<u8>, Located<BfError>> {
+    run_io(code, &input[..], vec![], config, &Limits::default()).into_result()
+}
+
+/// Runs `code` within `limits`, reading `input` as it goes and writing to `output` as soon as
+/// the program does. Output is flushed before every read so interactive programs show their
+/// prompts.
+///
+/// Steps are ops of the optimized program: a run of `+` or a multiply loop is a single step,
+/// but a scan loop like `[>]` takes one per cell it moves through.
+pub fn run_io<W: Write>(
+    code: &str,
+    input: impl BufRead,
+    output: W,
+    config: &BfConfig,
+    limits: &Limits,
+) -> Outcome<BfError, W> {
+    match ir::compile(code, config) {
+        Ok(program) => ir::run(&program, input, output, config, limits),
+        Err(err) => Outcome {
+            output,
+            steps: 0,
+            result: Err(err),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, BufReader, Read};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::brackets::BracketError;
+    use crate::error::Position;
+    use crate::limits::LimitExceeded;
+
+    fn error_at(code: &str, input: Vec<u8>, config: &BfConfig) -> (BfError, usize, usize) {
+        let Located {
+            error,
+            position: Position { line, column },
+        } = try_run(code, input, config).unwrap_err();
+        (error, line, column)
+    }
+
+    #[test]
+    fn comments() {
+        let code = "This prints an exclamation mark!\n+++++ +++++ [>+++<-] >+++. (33)";
+        assert_eq!(try_run(code, vec![], &BfConfig::default()), Ok(vec![33]));
+    }
+
+    #[test]
+    fn positioned_errors() {
+        let config = BfConfig::default();
+        assert_eq!(
+            error_at("+[\n  -]]", vec![], &config),
+            (BfError::Brackets(BracketError::UnmatchedClose(7)), 2, 5)
+        );
+        assert_eq!(
+            error_at("[[-]\n", vec![], &config),
+            (BfError::Brackets(BracketError::UnmatchedOpen(0)), 1, 1)
+        );
+        assert_eq!(
+            error_at("read: ,\nagain: ,", vec![1], &config),
+            (BfError::Eof, 2, 8)
+        );
+        let strict = BfConfig {
+            overflow: Overflow::Error,
+            tape: TapeModel::Bounded(4),
+            ..config
+        };
+        assert_eq!(
+            error_at("+\n++ --- -", vec![], &strict),
+            (BfError::Overflow, 2, 8)
+        );
+        assert_eq!(
+            error_at(">>>\n+[-<<+>>>>]", vec![], &strict),
+            (BfError::OutOfBounds, 2, 9)
+        );
+        // a loop turned into multiply-adds fails at its start
+        assert_eq!(
+            error_at(">>>\n+[-<<+>>>+<]", vec![], &strict),
+            (BfError::OutOfBounds, 2, 2)
+        );
+    }
+
+    /// Input that can only be read after the output so far has been flushed to `flushed`
+    struct Prompted<'a> {
+        flushed: &'a RefCell<Vec<u8>>,
+        expected: &'a [&'a [u8]],
+        replies: &'a [u8],
+    }
+
+    impl Read for Prompted<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((reply, replies)) = self.replies.split_first() else {
+                return Ok(0);
+            };
+            let (expected, rest) = self.expected.split_first().unwrap();
+            assert_eq!(&self.flushed.borrow()[..], *expected);
+            buf[0] = *reply;
+            (self.expected, self.replies) = (rest, replies);
+            Ok(1)
+        }
+    }
+
+    /// Collects the output, moving it to `flushed` on every flush
+    struct Output<'a> {
+        pending: Vec<u8>,
+        flushed: &'a RefCell<Vec<u8>>,
+    }
+
+    impl Write for Output<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.borrow_mut().append(&mut self.pending);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streams() {
+        // prints a prompt, then every byte it reads incremented until it reads a zero
+        let code = ">++++++++[<++++++++>-]<+.[-] ,[+.,]";
+        let flushed = RefCell::new(vec![]);
+        let input = Prompted {
+            flushed: &flushed,
+            expected: &[b"A", b"Ab", b"Abc"],
+            replies: b"ab\0",
+        };
+        let output = Output {
+            pending: vec![],
+            flushed: &flushed,
+        };
+        let config = BfConfig::default();
+        let input = BufReader::with_capacity(1, input);
+        let outcome = run_io(code, input, output, &config, &Limits::default());
+        assert_eq!(outcome.result, Ok(()));
+        assert_eq!(flushed.into_inner(), b"Abc");
+    }
+
+    #[test]
+    fn io_errors() {
+        struct Broken;
+
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let config = BfConfig::default();
+        let err = run_io("+\n++.", &[][..], Broken, &config, &Limits::default())
+            .result
+            .unwrap_err();
+        assert_eq!(
+            (err.error, err.position),
+            (
+                BfError::Io(io::ErrorKind::BrokenPipe),
+                Position { line: 2, column: 3 }
+            )
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let config = BfConfig::default();
+        let steps = |max_steps| Limits {
+            max_steps: Some(max_steps),
+            ..Limits::default()
+        };
+        // `+++` is one step, `[>]` is one plus one for every cell it moves by
+        let outcome = run_io("+++>+>+<<[>]<<<.", &[][..], vec![], &config, &steps(12));
+        assert_eq!(
+            (outcome.output, outcome.steps, outcome.result),
+            (vec![3], 12, Ok(()))
+        );
+        let outcome = run_io("+++.\n+[]", &[][..], vec![], &config, &steps(100));
+        assert_eq!(outcome.output, [3]);
+        assert_eq!(outcome.steps, 100);
+        assert_eq!(
+            outcome.result,
+            Err(Located {
+                error: BfError::LimitExceeded(LimitExceeded::Steps),
+                position: Position { line: 2, column: 3 },
+            })
+        );
+
+        let deadline = Limits {
+            deadline: Some(Instant::now() + Duration::from_millis(50)),
+            ..Limits::default()
+        };
+        let outcome = run_io(".+[]", &[][..], vec![], &config, &deadline);
+        assert_eq!(outcome.output, [0]);
+        assert_eq!(
+            outcome.result.map_err(|err| err.error),
+            Err(BfError::LimitExceeded(LimitExceeded::Deadline))
+        );
+    }
+}
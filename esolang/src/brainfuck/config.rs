@@ -0,0 +1,121 @@
+//! The brainfuck conventions that differ between implementations.
+
+use std::{fmt, io};
+
+use crate::brackets::BracketError;
+use crate::limits::LimitExceeded;
+use crate::tape::{OutOfBounds, TapeModel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellSize {
+    #[default]
+    U8,
+    U16,
+    U32,
+}
+
+impl CellSize {
+    pub fn max(self) -> u32 {
+        match self {
+            CellSize::U8 => u8::MAX as u32,
+            CellSize::U16 => u16::MAX as u32,
+            CellSize::U32 => u32::MAX,
+        }
+    }
+}
+
+/// What `+` on the largest value and `-` on zero do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Wrap,
+    Error,
+}
+
+/// What `,` does once the input is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Eof {
+    Unchanged,
+    Zero,
+    /// Sets the cell to its largest value
+    MinusOne,
+    #[default]
+    Error,
+}
+
+/// How a program is run. The default is what the kata's `brain_luck` does: wrapping `u8` cells
+/// on a tape infinite in both directions, reading past the input is an error.
+///
+/// Output always writes the lowest byte of the cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BfConfig {
+    pub cell: CellSize,
+    pub overflow: Overflow,
+    pub eof: Eof,
+    pub tape: TapeModel,
+}
+
+impl BfConfig {
+    /// `cell + delta`, with `delta` anything [`compile`](super::compile) produced for this config
+    #[inline]
+    pub(crate) fn add(&self, cell: u32, delta: i64) -> Result<u32, BfError> {
+        let max = self.cell.max() as i64;
+        match self.overflow {
+            Overflow::Wrap => Ok(((cell as i64).wrapping_add(delta) & max) as u32),
+            Overflow::Error => match (cell as i64).saturating_add(delta) {
+                sum @ 0.. if sum <= max => Ok(sum as u32),
+                _ => Err(BfError::Overflow),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BfError {
+    Brackets(BracketError),
+    Overflow,
+    Eof,
+    OutOfBounds,
+    /// Reading the input or writing the output failed
+    Io(io::ErrorKind),
+    LimitExceeded(LimitExceeded),
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BfError::Brackets(err) => return err.fmt(f),
+            BfError::Io(kind) => return write!(f, "i/o error: {kind}"),
+            BfError::LimitExceeded(limit) => return limit.fmt(f),
+            BfError::Overflow => "cell overflow",
+            BfError::Eof => "read past the end of input",
+            BfError::OutOfBounds => "pointer moved off the tape",
+        })
+    }
+}
+
+impl std::error::Error for BfError {}
+
+impl From<BracketError> for BfError {
+    fn from(err: BracketError) -> Self {
+        BfError::Brackets(err)
+    }
+}
+
+impl From<OutOfBounds> for BfError {
+    fn from(_: OutOfBounds) -> Self {
+        BfError::OutOfBounds
+    }
+}
+
+impl From<io::Error> for BfError {
+    fn from(err: io::Error) -> Self {
+        BfError::Io(err.kind())
+    }
+}
+
+impl From<LimitExceeded> for BfError {
+    fn from(limit: LimitExceeded) -> Self {
+        BfError::LimitExceeded(limit)
+    }
+}
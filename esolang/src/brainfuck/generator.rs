@@ -0,0 +1,446 @@
+//! Brainfuck written from higher level operations on named cells.
+//!
+//! Named cells get a tape cell of their own the first time they're used. Operations that need
+//! scratch space borrow free cells and hand them back cleared, so a cell that's not named is
+//! always zero between operations. The generated code assumes wrapping byte cells and only
+//! uses the tape to the right of where it starts.
+
+use std::collections::HashMap;
+
+/// Builds a brainfuck program one operation at a time
+#[derive(Debug, Clone, Default)]
+pub struct Generator {
+    code: String,
+    head: usize,
+    names: HashMap<String, usize>,
+    slots: Vec<Slot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Fresh,
+    /// Borrowed before. A name can't have it, the code that borrowed it may run again in a
+    /// loop after the name is set.
+    Free,
+    Taken,
+}
+
+/// Loops adding a constant stop paying off above this many iterations
+const MAX_FACTOR: i32 = 16;
+
+impl Generator {
+    /// The program so far
+    pub fn build(self) -> String {
+        self.code
+    }
+
+    fn cell(&mut self, name: &str) -> usize {
+        if let Some(&cell) = self.names.get(name) {
+            return cell;
+        }
+        let cell = self.take(|slot| slot == Slot::Fresh);
+        self.names.insert(name.to_string(), cell);
+        cell
+    }
+
+    /// The first cell whose slot is `available`, growing the tape if there's none
+    fn take(&mut self, available: impl Fn(Slot) -> bool) -> usize {
+        let cell = match self.slots.iter().position(|&slot| available(slot)) {
+            Some(cell) => cell,
+            None => {
+                self.slots.push(Slot::Fresh);
+                self.slots.len() - 1
+            }
+        };
+        self.slots[cell] = Slot::Taken;
+        cell
+    }
+
+    /// A cell that's zero to borrow
+    fn alloc(&mut self) -> usize {
+        self.take(|slot| slot != Slot::Taken)
+    }
+
+    /// Returns a cell from [`alloc`](Self::alloc), which has to be zero again
+    fn free(&mut self, cell: usize) {
+        self.slots[cell] = Slot::Free;
+    }
+
+    fn goto(&mut self, cell: usize) {
+        let (ch, n) = if cell > self.head {
+            ('>', cell - self.head)
+        } else {
+            ('<', self.head - cell)
+        };
+        self.code.extend(std::iter::repeat_n(ch, n));
+        self.head = cell;
+    }
+
+    /// Adds `n` to `cell` with plain `+` or `-`, whichever is shorter
+    fn add_direct(&mut self, cell: usize, n: i32) {
+        let n = n.rem_euclid(256);
+        self.goto(cell);
+        let (ch, count) = if n <= 128 { ('+', n) } else { ('-', 256 - n) };
+        self.code.extend(std::iter::repeat_n(ch, count as usize));
+    }
+
+    /// Adds `n` to `cell`, as `a * b + c` with a loop running `a` times if that's shorter
+    fn add_factored(&mut self, cell: usize, n: i32) {
+        let mut best = self.with_code(|g| g.add_direct(cell, n));
+        let n = n.rem_euclid(256);
+        for delta in [n, n - 256] {
+            for a in 2..=MAX_FACTOR.min(delta.abs()) {
+                let b = (delta as f64 / a as f64).round() as i32;
+                let c = delta - a * b;
+                let candidate = self.with_code(|g| {
+                    let counter = g.alloc();
+                    g.add_direct(counter, a);
+                    g.code.push('[');
+                    g.add_direct(cell, b);
+                    g.goto(counter);
+                    g.code.push_str("-]");
+                    g.free(counter);
+                    g.add_direct(cell, c);
+                });
+                if candidate.code.len() < best.code.len() {
+                    best = candidate;
+                }
+            }
+        }
+        self.code.push_str(&best.code);
+        self.head = best.head;
+        self.slots = best.slots;
+    }
+
+    /// What `f` would emit from the current state, with the code starting out empty
+    fn with_code(&self, f: impl FnOnce(&mut Self)) -> Self {
+        let mut g = Generator {
+            code: String::new(),
+            head: self.head,
+            names: HashMap::new(),
+            slots: self.slots.clone(),
+        };
+        f(&mut g);
+        g
+    }
+
+    fn clear(&mut self, cell: usize) {
+        self.goto(cell);
+        self.code.push_str("[-]");
+    }
+
+    /// Empties `from` into the `targets`, adding it to each times its factor
+    fn move_to(&mut self, from: usize, targets: &[(usize, i32)]) {
+        self.goto(from);
+        self.code.push_str("[-");
+        for &(cell, factor) in targets {
+            self.add_direct(cell, factor);
+        }
+        self.goto(from);
+        self.code.push(']');
+    }
+
+    /// Adds `from` times `factor` to `to`, leaving `from` as it was
+    fn add_times(&mut self, from: usize, to: usize, factor: i32) {
+        assert_ne!(from, to, "a cell can't be added to itself");
+        let tmp = self.alloc();
+        self.move_to(from, &[(to, factor), (tmp, 1)]);
+        self.move_to(tmp, &[(from, 1)]);
+        self.free(tmp);
+    }
+
+    /// Sets `name` to `value`
+    pub fn set(&mut self, name: &str, value: u8) -> &mut Self {
+        let cell = self.cell(name);
+        self.clear(cell);
+        self.add_factored(cell, value as i32);
+        self
+    }
+
+    /// Adds `n` to `name`, wrapping around
+    pub fn add_const(&mut self, name: &str, n: i32) -> &mut Self {
+        let cell = self.cell(name);
+        self.add_factored(cell, n);
+        self
+    }
+
+    /// Sets `to` to the value of `from`
+    pub fn copy(&mut self, from: &str, to: &str) -> &mut Self {
+        let (from, to) = (self.cell(from), self.cell(to));
+        if from != to {
+            self.clear(to);
+            self.add_times(from, to, 1);
+        }
+        self
+    }
+
+    /// Adds `from` to `to`, which has to be a different cell
+    pub fn add(&mut self, from: &str, to: &str) -> &mut Self {
+        let (from, to) = (self.cell(from), self.cell(to));
+        self.add_times(from, to, 1);
+        self
+    }
+
+    /// Subtracts `from` from `to`, which has to be a different cell
+    pub fn sub(&mut self, from: &str, to: &str) -> &mut Self {
+        let (from, to) = (self.cell(from), self.cell(to));
+        self.add_times(from, to, -1);
+        self
+    }
+
+    /// Sets `to` to `a * b`, `to` has to be a different cell from both
+    pub fn mul(&mut self, a: &str, b: &str, to: &str) -> &mut Self {
+        let (a, b, to) = (self.cell(a), self.cell(b), self.cell(to));
+        assert!(to != a && to != b, "the product needs a cell of its own");
+        self.clear(to);
+        let counter = self.alloc();
+        self.add_times(a, counter, 1);
+        self.goto(counter);
+        self.code.push_str("[-");
+        self.add_times(b, to, 1);
+        self.goto(counter);
+        self.code.push(']');
+        self.free(counter);
+        self
+    }
+
+    /// Reads a byte into `name`
+    pub fn read(&mut self, name: &str) -> &mut Self {
+        let cell = self.cell(name);
+        self.goto(cell);
+        self.code.push(',');
+        self
+    }
+
+    /// Outputs `name` as a byte
+    pub fn print_cell(&mut self, name: &str) -> &mut Self {
+        let cell = self.cell(name);
+        self.goto(cell);
+        self.code.push('.');
+        self
+    }
+
+    /// Outputs `text`, moving a scratch cell from one byte to the next
+    pub fn print(&mut self, text: &str) -> &mut Self {
+        let cell = self.alloc();
+        let mut value = 0;
+        for byte in text.bytes() {
+            self.add_factored(cell, byte as i32 - value);
+            self.code.push('.');
+            value = byte as i32;
+        }
+        if value != 0 {
+            self.clear(cell);
+        }
+        self.free(cell);
+        self
+    }
+
+    /// Outputs `name` in decimal
+    pub fn print_decimal(&mut self, name: &str) -> &mut Self {
+        let cell = self.cell(name);
+        let [ones, tens, hundreds, count, any] = [(); 5].map(|()| self.alloc());
+        // count up to the value in three decimal digits
+        self.add_times(cell, count, 1);
+        self.while_cell(count, |g| {
+            g.add_direct(count, -1);
+            g.add_direct(ones, 1);
+            g.carry(ones, tens, |g| g.carry(tens, hundreds, |_| {}));
+        });
+        // leading zeros are skipped, but a zero value still prints one
+        self.if_nonzero_cell(hundreds, |g| {
+            g.add_direct(any, 1);
+            g.print_digit(hundreds);
+        });
+        self.if_nonzero_cell(tens, |g| {
+            g.clear(any);
+            g.add_direct(any, 1);
+        });
+        self.if_nonzero_cell(any, |g| g.print_digit(tens));
+        self.print_digit(ones);
+        for cell in [ones, tens, hundreds, any] {
+            self.clear(cell);
+            self.free(cell);
+        }
+        self.free(count);
+        self
+    }
+
+    /// When `digit` reaches ten, sets it back to zero, adds one to `next` and runs `then`
+    fn carry(&mut self, digit: usize, next: usize, then: impl FnOnce(&mut Self)) {
+        let ten = self.alloc();
+        self.add_times(digit, ten, 1);
+        self.add_direct(ten, -10);
+        self.if_zero_cell(ten, |g| {
+            g.clear(digit);
+            g.add_direct(next, 1);
+            then(g);
+        });
+        self.clear(ten);
+        self.free(ten);
+    }
+
+    fn print_digit(&mut self, cell: usize) {
+        self.add_factored(cell, b'0' as i32);
+        self.code.push('.');
+        self.add_factored(cell, -(b'0' as i32));
+    }
+
+    /// Runs `body` if `name` isn't zero
+    pub fn if_nonzero(&mut self, name: &str, body: impl FnOnce(&mut Self)) -> &mut Self {
+        let cell = self.cell(name);
+        self.if_nonzero_cell(cell, body);
+        self
+    }
+
+    fn if_nonzero_cell(&mut self, cell: usize, body: impl FnOnce(&mut Self)) {
+        let flag = self.alloc();
+        self.add_times(cell, flag, 1);
+        self.goto(flag);
+        self.code.push('[');
+        body(self);
+        self.clear(flag);
+        self.code.push(']');
+        self.free(flag);
+    }
+
+    /// Runs `body` if `name` is zero
+    pub fn if_zero(&mut self, name: &str, body: impl FnOnce(&mut Self)) -> &mut Self {
+        let cell = self.cell(name);
+        self.if_zero_cell(cell, body);
+        self
+    }
+
+    fn if_zero_cell(&mut self, cell: usize, body: impl FnOnce(&mut Self)) {
+        let flag = self.alloc();
+        let copy = self.alloc();
+        self.add_direct(flag, 1);
+        self.add_times(cell, copy, 1);
+        self.goto(copy);
+        self.code.push('[');
+        self.clear(flag);
+        self.clear(copy);
+        self.code.push(']');
+        self.free(copy);
+        self.goto(flag);
+        self.code.push('[');
+        body(self);
+        self.clear(flag);
+        self.code.push(']');
+        self.free(flag);
+    }
+
+    /// Runs `body` for as long as `name` isn't zero, checking before every iteration
+    pub fn while_nonzero(&mut self, name: &str, body: impl FnOnce(&mut Self)) -> &mut Self {
+        let cell = self.cell(name);
+        self.while_cell(cell, body);
+        self
+    }
+
+    fn while_cell(&mut self, cell: usize, body: impl FnOnce(&mut Self)) {
+        self.goto(cell);
+        self.code.push('[');
+        body(self);
+        self.goto(cell);
+        self.code.push(']');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brainfuck::{try_run, BfConfig};
+    use crate::test_rng::Rng;
+
+    fn output(generator: &mut Generator, input: &[u8]) -> Vec<u8> {
+        let code = std::mem::take(generator).build();
+        try_run(&code, input.to_vec(), &BfConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn prints_text() {
+        for text in [
+            "Hello, World!\n",
+            "",
+            "zzz",
+            "\0\u{7f}",
+            "caf\u{e9} \u{1f980}",
+        ] {
+            let mut g = Generator::default();
+            g.print(text);
+            assert_eq!(output(&mut g, b""), text.as_bytes(), "{text:?}");
+        }
+        // factoring beats plain `+` by a lot on text
+        let mut g = Generator::default();
+        g.print("Hello, World!");
+        let code = g.build();
+        assert!(code.len() < 200, "{} {code}", code.len());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..50 {
+            let (a, b, n) = (
+                rng.below(256) as u8,
+                rng.below(256) as u8,
+                rng.below(600) as i32,
+            );
+            let mut g = Generator::default();
+            g.set("a", a)
+                .set("b", b)
+                .copy("a", "c")
+                .add("b", "c")
+                .print_cell("c");
+            g.copy("a", "d").sub("b", "d").print_cell("d");
+            g.mul("a", "b", "e").print_cell("e");
+            g.add_const("a", n - 300).print_cell("a").print_cell("b");
+            assert_eq!(
+                output(&mut g, b""),
+                [
+                    a.wrapping_add(b),
+                    a.wrapping_sub(b),
+                    a.wrapping_mul(b),
+                    (a as i32 + n - 300).rem_euclid(256) as u8,
+                    b
+                ],
+                "{a} {b} {n}"
+            );
+        }
+    }
+
+    #[test]
+    fn decimal() {
+        let mut g = Generator::default();
+        g.set("n", 0);
+        for _ in 0..256 {
+            g.print_decimal("n").print(" ").add_const("n", 1);
+        }
+        let expected: String = (0..=255).map(|n| format!("{n} ")).collect();
+        assert_eq!(output(&mut g, b""), expected.as_bytes());
+    }
+
+    #[test]
+    fn control_flow() {
+        // counts down from the byte read, saying which numbers are even
+        let mut g = Generator::default();
+        g.read("n");
+        g.while_nonzero("n", |g| {
+            g.print_decimal("n").set("even", 1).copy("n", "m");
+            g.while_nonzero("m", |g| {
+                g.add_const("m", -1);
+                g.if_zero("m", |g| {
+                    g.set("even", 0);
+                });
+                g.if_nonzero("m", |g| {
+                    g.add_const("m", -1);
+                });
+            });
+            g.if_nonzero("even", |g| {
+                g.print(" even");
+            });
+            g.print("\n").add_const("n", -1);
+        });
+        assert_eq!(output(&mut g, &[5]), b"5\n4 even\n3\n2 even\n1\n");
+    }
+}
@@ -0,0 +1,532 @@
+//! Brainfuck compiled to a small IR before running it.
+//!
+//! Runs of `+`/`-` and `>`/`<` are folded into a single op, and a few loop shapes are replaced
+//! by straight-line ops: `[-]` clears the cell, `[>]` scans for a zero cell, and balanced loops
+//! that only add to other cells while decrementing the current one by 1 (like `[->+>++<<]`)
+//! become multiply-adds followed by a clear.
+//!
+//! When overflow or leaving a bounded tape is an error, the affected runs aren't folded so that
+//! an error points at the exact character that failed. Loops are still replaced, as long as
+//! every cell in them only moves one way, and errors inside them are reported at their `[`.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use super::{BfConfig, BfError, Eof, Overflow};
+use crate::brackets::match_brackets;
+use crate::error::{Located, Position};
+use crate::limits::{Budget, Limits, Outcome};
+use crate::tape::{DenseTape, Tape, TapeModel};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    /// Masked to the cell size when cells wrap, signed otherwise
+    Add(i64),
+    Move(isize),
+    Clear,
+    /// `tape[ptr + offset] += tape[ptr] * factor`, skipped when the current cell is zero
+    MulAdd(isize, i64),
+    /// Moves by the step until the current cell is zero
+    Scan(isize),
+    Out,
+    In,
+    /// Jumps past the matching `Close` if the current cell is zero
+    Open(usize),
+    /// Jumps back past the matching `Open` if the current cell isn't zero
+    Close(usize),
+}
+
+/// Whether two changes to a cell can be merged without hiding an overflow in between
+fn mergeable(checked: bool, a: i64, b: i64) -> bool {
+    !checked || a == 0 || (a < 0) == (b < 0)
+}
+
+/// Straight-line replacement for a loop body, if it has one
+fn optimize_loop(body: &[Op], config: &BfConfig) -> Option<Vec<Op>> {
+    let checked = config.overflow == Overflow::Error;
+    let minus_one = if checked {
+        -1
+    } else {
+        config.cell.max() as i64
+    };
+    match body {
+        [Op::Add(n)] if *n == minus_one || (!checked && *n == 1) => return Some(vec![Op::Clear]),
+        [Op::Move(step)] => return Some(vec![Op::Scan(*step)]),
+        _ => {}
+    }
+    let (mut offset, mut lowest, mut highest) = (0, 0, 0);
+    let mut deltas = BTreeMap::new();
+    for op in body {
+        match op {
+            Op::Add(n) => {
+                let delta: &mut i64 = deltas.entry(offset).or_default();
+                if !mergeable(checked, *delta, *n) {
+                    return None;
+                }
+                *delta += n;
+                if !checked {
+                    *delta &= config.cell.max() as i64;
+                }
+            }
+            Op::Move(n) => {
+                offset += n;
+                lowest = lowest.min(offset);
+                highest = highest.max(offset);
+            }
+            _ => return None,
+        }
+    }
+    if offset != 0 || deltas.get(&0) != Some(&minus_one) {
+        return None;
+    }
+    // the multiply-adds have to touch both ends of the loop for the bounds check to fire
+    if matches!(config.tape, TapeModel::Bounded(_))
+        && [lowest, highest]
+            .iter()
+            .any(|end| *end != 0 && deltas.get(end).is_none_or(|delta| *delta == 0))
+    {
+        return None;
+    }
+    // on a wrapping tape an offset can land back on the counter or on another offset's cell, in
+    // which case the loop doesn't run a fixed number of times or the adds don't commute
+    if let TapeModel::Wrap(len) = config.tape {
+        let mut cells: Vec<_> = deltas.keys().map(|o| o.rem_euclid(len as isize)).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        if cells.len() != deltas.len() {
+            return None;
+        }
+    }
+    let mut ops: Vec<_> = deltas
+        .into_iter()
+        .filter(|&(offset, delta)| offset != 0 && delta != 0)
+        .map(|(offset, delta)| Op::MulAdd(offset, delta))
+        .collect();
+    ops.push(Op::Clear);
+    Some(ops)
+}
+
+pub struct Program {
+    pub(super) ops: Vec<Op>,
+    /// Where in the source every op starts
+    positions: Vec<Position>,
+    /// Just past the end of the source, where the final flush fails
+    end: Position,
+}
+
+impl Program {
+    fn push(&mut self, op: Op, position: Position) {
+        self.ops.push(op);
+        self.positions.push(position);
+    }
+
+    fn pop(&mut self) {
+        self.ops.pop();
+        self.positions.pop();
+    }
+}
+
+/// Compiles `code`, anything but the eight commands is a comment
+pub fn compile(code: &str, config: &BfConfig) -> Result<Program, Located<BfError>> {
+    match_brackets(&code.chars().collect::<Vec<_>>()).map_err(|err| Located {
+        error: err.into(),
+        position: Position::of(code, err.index()),
+    })?;
+    let checked = config.overflow == Overflow::Error;
+    let bounded = matches!(config.tape, TapeModel::Bounded(_));
+    let mut program = Program {
+        ops: vec![],
+        positions: vec![],
+        end: Position::START,
+    };
+    let mut opens = vec![];
+    let mut position = Position::START;
+    for ch in code.chars() {
+        let at = position;
+        position = position.after(ch);
+        match ch {
+            '+' | '-' => {
+                let n = if ch == '+' { 1 } else { -1 };
+                match program.ops.last_mut() {
+                    Some(Op::Add(m)) if !checked => {
+                        *m = (*m + n) & config.cell.max() as i64;
+                        if *m == 0 {
+                            program.pop();
+                        }
+                    }
+                    _ if checked => program.push(Op::Add(n), at),
+                    _ => program.push(Op::Add(n & config.cell.max() as i64), at),
+                }
+            }
+            '>' | '<' => {
+                let n = if ch == '>' { 1 } else { -1 };
+                match program.ops.last_mut() {
+                    Some(Op::Move(m)) if !bounded => {
+                        *m += n;
+                        if *m == 0 {
+                            program.pop();
+                        }
+                    }
+                    _ => program.push(Op::Move(n), at),
+                }
+            }
+            '.' => program.push(Op::Out, at),
+            ',' => program.push(Op::In, at),
+            '[' => {
+                opens.push(program.ops.len());
+                program.push(Op::Open(0), at);
+            }
+            ']' => {
+                let open = opens.pop().unwrap();
+                match optimize_loop(&program.ops[open + 1..], config) {
+                    Some(replacement) => {
+                        let at = program.positions[open];
+                        program.ops.truncate(open);
+                        program.positions.truncate(open);
+                        for op in replacement {
+                            program.push(op, at);
+                        }
+                    }
+                    None => {
+                        program.ops[open] = Op::Open(program.ops.len());
+                        program.push(Op::Close(open), at);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    program.end = position;
+    Ok(program)
+}
+
+/// Runs `program` reading `input` and writing `output`. Output is flushed before every read
+/// and at the end.
+pub fn run<W: Write>(
+    program: &Program,
+    input: impl BufRead,
+    mut output: W,
+    config: &BfConfig,
+    limits: &Limits,
+) -> Outcome<BfError, W> {
+    let mut pc = 0;
+    let mut budget = Budget::new(limits);
+    let result = execute(
+        &program.ops,
+        input,
+        &mut output,
+        config,
+        &mut pc,
+        &mut budget,
+    );
+    Outcome {
+        output,
+        steps: budget.steps,
+        result: result.map_err(|error| Located {
+            error,
+            position: program.positions.get(pc).copied().unwrap_or(program.end),
+        }),
+    }
+}
+
+/// Runs `ops` leaving `pc` at the failing op, if any
+fn execute(
+    ops: &[Op],
+    input: impl BufRead,
+    output: &mut impl Write,
+    config: &BfConfig,
+    pc: &mut usize,
+    budget: &mut Budget,
+) -> Result<(), BfError> {
+    let mut input = input.bytes();
+    let mut tape = DenseTape::new(config.tape);
+    while *pc < ops.len() {
+        budget.step()?;
+        match ops[*pc] {
+            Op::Add(n) => {
+                let cell = tape.current();
+                *cell = config.add(*cell, n)?;
+            }
+            Op::Move(n) => tape.shift(n)?,
+            Op::Clear => tape.set(0),
+            Op::MulAdd(offset, factor) => {
+                let val = tape.get() as i64;
+                if val != 0 {
+                    let delta = match config.overflow {
+                        Overflow::Wrap => val.wrapping_mul(factor),
+                        Overflow::Error => val.saturating_mul(factor),
+                    };
+                    let cell = tape.at(offset)?;
+                    *cell = config.add(*cell, delta)?;
+                }
+            }
+            Op::Scan(step) => {
+                while tape.get() != 0 {
+                    budget.step()?;
+                    tape.shift(step)?;
+                }
+            }
+            Op::Out => output.write_all(&[tape.get() as u8])?,
+            Op::In => match (output.flush().and(input.next().transpose())?, config.eof) {
+                (Some(byte), _) => tape.set(byte as u32),
+                (None, Eof::Unchanged) => {}
+                (None, Eof::Zero) => tape.set(0),
+                (None, Eof::MinusOne) => tape.set(config.cell.max()),
+                (None, Eof::Error) => return Err(BfError::Eof),
+            },
+            Op::Open(close) => {
+                if tape.get() == 0 {
+                    *pc = close;
+                }
+            }
+            Op::Close(open) => {
+                if tape.get() != 0 {
+                    *pc = open;
+                }
+            }
+        }
+        *pc += 1;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::brainfuck::CellSize;
+    use crate::tape::SparseTape;
+
+    const HELLO_WORLD: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+    /// Nested loops whose innermost bodies are multiply loops
+    const NESTED: &str = "-[>-[>++++++++++++++++[>+>+<<-]>[<+>-]<<-]<-]>>>.";
+
+    fn interpret(code: &str, input: Vec<u8>, config: &BfConfig) -> Result<Vec<u8>, BfError> {
+        run(
+            &compile(code, config).unwrap(),
+            &input[..],
+            vec![],
+            config,
+            &Limits::default(),
+        )
+        .into_result()
+        .map_err(|err| err.error)
+    }
+
+    /// The straightforward interpreter over a sparse tape the IR has to agree with
+    fn reference(code: &str, input: Vec<u8>, config: &BfConfig) -> Result<Vec<u8>, BfError> {
+        let mut input = input.into_iter();
+        let code: Vec<_> = code.chars().collect();
+        let jumps = match_brackets(&code)?;
+        let mut tape: SparseTape<u32> = SparseTape::new(config.tape);
+        let (mut pc, mut output) = (0, vec![]);
+        while pc < code.len() {
+            match code[pc] {
+                '>' => tape.shift(1)?,
+                '<' => tape.shift(-1)?,
+                '+' | '-' => {
+                    let cell = tape.current();
+                    *cell = config.add(*cell, if code[pc] == '+' { 1 } else { -1 })?;
+                }
+                '.' => output.push(tape.get() as u8),
+                ',' => match (input.next(), config.eof) {
+                    (Some(byte), _) => tape.set(byte as u32),
+                    (None, Eof::Unchanged) => {}
+                    (None, Eof::Zero) => tape.set(0),
+                    (None, Eof::MinusOne) => tape.set(config.cell.max()),
+                    (None, Eof::Error) => return Err(BfError::Eof),
+                },
+                '[' if tape.get() == 0 => pc = jumps[pc],
+                ']' if tape.get() != 0 => pc = jumps[pc],
+                _ => {}
+            }
+            pc += 1;
+        }
+        Ok(output)
+    }
+
+    fn configs() -> Vec<BfConfig> {
+        let mut configs = vec![];
+        for cell in [CellSize::U8, CellSize::U16, CellSize::U32] {
+            for overflow in [Overflow::Wrap, Overflow::Error] {
+                for eof in [Eof::Unchanged, Eof::Zero, Eof::MinusOne, Eof::Error] {
+                    for tape in [
+                        TapeModel::Infinite,
+                        TapeModel::Wrap(8),
+                        TapeModel::Bounded(8),
+                    ] {
+                        configs.push(BfConfig {
+                            cell,
+                            overflow,
+                            eof,
+                            tape,
+                        });
+                    }
+                }
+            }
+        }
+        configs
+    }
+
+    #[test]
+    fn folds_runs() {
+        let config = BfConfig::default();
+        assert_eq!(
+            compile("+++-->>><<.+-,", &config).unwrap().ops,
+            vec![Op::Add(1), Op::Move(1), Op::Out, Op::In]
+        );
+        let checked = BfConfig {
+            overflow: Overflow::Error,
+            tape: TapeModel::Bounded(10),
+            ..config
+        };
+        assert_eq!(
+            compile("++><", &checked).unwrap().ops,
+            vec![Op::Add(1), Op::Add(1), Op::Move(1), Op::Move(-1)]
+        );
+    }
+
+    #[test]
+    fn optimizes_loops() {
+        let config = BfConfig::default();
+        assert_eq!(compile("[-]", &config).unwrap().ops, vec![Op::Clear]);
+        assert_eq!(compile("[+]", &config).unwrap().ops, vec![Op::Clear]);
+        assert_eq!(compile("[<<]", &config).unwrap().ops, vec![Op::Scan(-2)]);
+        assert_eq!(
+            compile("[->+>+++<<]", &config).unwrap().ops,
+            vec![Op::MulAdd(1, 1), Op::MulAdd(2, 3), Op::Clear]
+        );
+        assert_eq!(
+            compile("[>-<-]", &config).unwrap().ops,
+            vec![Op::MulAdd(1, 255), Op::Clear]
+        );
+        let wide = BfConfig {
+            cell: CellSize::U16,
+            ..config
+        };
+        assert_eq!(
+            compile("[>-<-]", &wide).unwrap().ops,
+            vec![Op::MulAdd(1, 65535), Op::Clear]
+        );
+        // unbalanced and non-decrementing loops are kept
+        assert_eq!(
+            compile("[->+]", &config).unwrap().ops,
+            vec![
+                Op::Open(4),
+                Op::Add(255),
+                Op::Move(1),
+                Op::Add(1),
+                Op::Close(0)
+            ]
+        );
+        assert_eq!(
+            compile("[+>+<]", &config).unwrap().ops,
+            vec![
+                Op::Open(5),
+                Op::Add(1),
+                Op::Move(1),
+                Op::Add(1),
+                Op::Move(-1),
+                Op::Close(0)
+            ]
+        );
+        // `[+]` overflows unless the cell is already zero
+        let checked = BfConfig {
+            overflow: Overflow::Error,
+            ..config
+        };
+        assert_eq!(compile("[-]", &checked).unwrap().ops, vec![Op::Clear]);
+        assert_eq!(
+            compile("[+]", &checked).unwrap().ops,
+            vec![Op::Open(2), Op::Add(1), Op::Close(0)]
+        );
+        // the loop passes offset 3 without adding to it
+        let bounded = BfConfig {
+            tape: TapeModel::Bounded(10),
+            ..config
+        };
+        assert_eq!(compile("[->>><-<<]", &bounded).unwrap().ops.len(), 10);
+        assert_eq!(
+            compile("[->>-<<]", &bounded).unwrap().ops,
+            vec![Op::MulAdd(2, 255), Op::Clear]
+        );
+    }
+
+    #[test]
+    fn wrapping_offsets() {
+        let config = BfConfig {
+            tape: TapeModel::Wrap(8),
+            ..BfConfig::default()
+        };
+        // offset 8 is the counter itself, 1 and 9 are the same cell
+        for code in ["+[->+>>>>>>>++<<<<<<<<]>.", "++[->+>>>>>>>>+<<<<<<<<<]>."] {
+            let ops = compile(code, &config).unwrap().ops;
+            assert!(ops.iter().any(|op| matches!(op, Op::Close(_))), "{code}");
+            assert_eq!(
+                interpret(code, vec![], &config),
+                reference(code, vec![], &config),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn grows_both_ways() {
+        let config = BfConfig::default();
+        let code = format!("<<<<+++.{}.{}.", ">".repeat(100), "<".repeat(100));
+        assert_eq!(interpret(&code, vec![], &config), Ok(vec![3, 0, 3]));
+    }
+
+    #[test]
+    fn same_output_as_reference() {
+        for (code, input) in [
+            (HELLO_WORLD, vec![]),
+            (
+                "+++[>++++[>++++++++++++++++[>+>+<<-]>[<+>-]<<-]<-]>>>.",
+                vec![],
+            ),
+            (",>,<[>[->+>+<<]>>[-<<+>>]<<<-]>>.", vec![8, 9]),
+            ("<<<,[>>>+<<<-]>>>[<+<+>>-]<[.-]", vec![5]),
+            (",.,.,.,.", b"ab".to_vec()),
+            (",.,.>,.", vec![7]),
+            ("-.+-+.[-]+[>>>+<<<-]>>>.>>>.", vec![]),
+            ("+++[->>>>+<<<<]>>>>.<<<<<.", vec![]),
+            (">>>>>>>>>+.[<]+.", vec![]),
+            (
+                "++++++++[>++++++++<-]>[>++++<-]>[>++++<-]>.[-].+[[-]>+<]>.",
+                vec![],
+            ),
+        ] {
+            for config in configs() {
+                assert_eq!(
+                    interpret(code, input.clone(), &config),
+                    reference(code, input.clone(), &config),
+                    "{code} {config:?}"
+                );
+            }
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_against_reference() {
+        let config = BfConfig::default();
+        for (name, code) in [("hello world", HELLO_WORLD), ("nested loops", NESTED)] {
+            let start = Instant::now();
+            let expected = reference(code, vec![], &config);
+            let interpreted = start.elapsed();
+            let start = Instant::now();
+            let actual = interpret(code, vec![], &config);
+            let compiled = start.elapsed();
+            assert_eq!(actual, expected);
+            println!(
+                "{name}: reference {interpreted:?}, ir {compiled:?} ({:.1}x)",
+                interpreted.as_secs_f64() / compiled.as_secs_f64()
+            );
+        }
+    }
+}
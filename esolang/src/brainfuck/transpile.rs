@@ -0,0 +1,207 @@
+//! Brainfuck to standalone Rust or C source.
+//!
+//! The program is compiled to the same IR the interpreter runs and every op becomes a line of
+//! code, loops become `while` loops. The generated program has the default [`BfConfig`]
+//! semantics, except that the tape is an array of 65536 cells the pointer wraps around in,
+//! starting in the middle. It reads stdin, writes stdout and exits with status 1 when it reads
+//! past the end of its input.
+
+use super::ir::{compile, Op};
+use super::{BfConfig, BfError};
+use crate::error::Located;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Rust,
+    C,
+}
+
+const RUST_PRELUDE: &str = "\
+// programs that don't read or write leave some of this unused
+#![allow(unused)]
+
+use std::io::{Bytes, Read, StdinLock, Write};
+
+fn read(input: &mut Bytes<StdinLock<'static>>) -> u8 {
+    match input.next() {
+        Some(Ok(byte)) => byte,
+        _ => {
+            eprintln!(\"read past the end of input\");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut tape = [0u8; 1 << 16];
+    let mut p: usize = 1 << 15;
+    let mut input = std::io::stdin().lock().bytes();
+    let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+";
+
+const RUST_EPILOGUE: &str = "    output.flush().unwrap();\n}\n";
+
+const C_PRELUDE: &str = "\
+#include <stdio.h>
+
+static unsigned char tape[65536];
+
+int main(void) {
+    unsigned short p = 32768;
+    int c;
+";
+
+const C_EPILOGUE: &str = "    return 0;\n}\n";
+
+/// The line of `target` code for a single op
+fn line(target: Target, op: &Op) -> String {
+    // offsets are taken modulo the tape length so that they are never negative
+    let wrap = |offset: isize| offset.rem_euclid(1 << 16);
+    match (target, op) {
+        (Target::Rust, Op::Add(n)) => format!("tape[p] = tape[p].wrapping_add({n});"),
+        (Target::Rust, Op::Move(n)) => format!("p = (p + {}) & 0xffff;", wrap(*n)),
+        (Target::Rust, Op::Clear) => "tape[p] = 0;".to_string(),
+        (Target::Rust, Op::MulAdd(offset, factor)) => {
+            let at = format!("tape[(p + {}) & 0xffff]", wrap(*offset));
+            format!("{at} = {at}.wrapping_add(tape[p].wrapping_mul({factor}));")
+        }
+        (Target::Rust, Op::Scan(step)) => {
+            format!(
+                "while tape[p] != 0 {{ p = (p + {}) & 0xffff; }}",
+                wrap(*step)
+            )
+        }
+        (Target::Rust, Op::Out) => "output.write_all(&[tape[p]]).unwrap();".to_string(),
+        (Target::Rust, Op::In) => {
+            "output.flush().unwrap();\ntape[p] = read(&mut input);".to_string()
+        }
+        (Target::Rust, Op::Open(_)) => "while tape[p] != 0 {".to_string(),
+        (Target::C, Op::Add(n)) => format!("tape[p] += {n};"),
+        (Target::C, Op::Move(n)) => format!("p += {};", wrap(*n)),
+        (Target::C, Op::Clear) => "tape[p] = 0;".to_string(),
+        (Target::C, Op::MulAdd(offset, factor)) => format!(
+            "tape[(unsigned short)(p + {})] += tape[p] * {factor};",
+            wrap(*offset)
+        ),
+        (Target::C, Op::Scan(step)) => format!("while (tape[p]) p += {};", wrap(*step)),
+        (Target::C, Op::Out) => "putchar(tape[p]);".to_string(),
+        (Target::C, Op::In) => "fflush(stdout);\n\
+            if ((c = getchar()) == EOF) {\n    \
+                fputs(\"read past the end of input\\n\", stderr);\n    \
+                return 1;\n\
+            }\n\
+            tape[p] = c;"
+            .to_string(),
+        (Target::C, Op::Open(_)) => "while (tape[p]) {".to_string(),
+        (_, Op::Close(_)) => "}".to_string(),
+    }
+}
+
+/// Source of a `target` program doing what the brainfuck `code` does
+pub fn transpile(code: &str, target: Target) -> Result<String, Located<BfError>> {
+    let program = compile(code, &BfConfig::default())?;
+    let (prelude, epilogue) = match target {
+        Target::Rust => (RUST_PRELUDE, RUST_EPILOGUE),
+        Target::C => (C_PRELUDE, C_EPILOGUE),
+    };
+    let mut source = prelude.to_string();
+    let mut depth = 1;
+    for op in &program.ops {
+        if let Op::Close(_) = op {
+            depth -= 1;
+        }
+        for line in line(target, op).lines() {
+            source.push_str(&"    ".repeat(depth));
+            source.push_str(line);
+            source.push('\n');
+        }
+        if let Op::Open(_) = op {
+            depth += 1;
+        }
+    }
+    source.push_str(epilogue);
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    use super::*;
+    use crate::brainfuck::try_run;
+
+    const CORPUS: &[(&str, &[u8])] = &[
+        ("++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.", b""),
+        (",>,<[>[->+>+<<]>>[-<<+>>]<<<-]>>.", &[8, 9]),
+        (",+[-.,+]", b"Codewars\xff"),
+        (",[.[-],]", b"Codewars\0"),
+        ("Moves left of the start: <<<<+++[>+++<-]>.<[-]-.", b""),
+        ("-[>-[>++++++++++++++++[>+>+<<-]>[<+>-]<<-]<-]>>>.", b""),
+    ];
+
+    /// Feeds `input` to the program at `exe` and collects its stdout
+    fn run_exe(exe: &Path, input: &[u8]) -> Vec<u8> {
+        let mut child = Command::new(exe)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        output.stdout
+    }
+
+    /// Builds every program in the corpus with `build(source, exe)` and compares its output
+    /// with the interpreter's
+    fn check(target: Target, extension: &str, build: impl Fn(&Path, &Path) -> Command) {
+        let dir =
+            std::env::temp_dir().join(format!("bf-transpile-{extension}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (i, (code, input)) in CORPUS.iter().enumerate() {
+            let src = dir.join(format!("prog{i}.{extension}"));
+            let exe = dir.join(format!("prog{i}"));
+            fs::write(&src, transpile(code, target).unwrap()).unwrap();
+            let status = build(&src, &exe).status().unwrap();
+            assert!(status.success(), "{code}");
+            let expected = try_run(code, input.to_vec(), &BfConfig::default()).unwrap();
+            assert_eq!(run_exe(&exe, input), expected, "{code}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rust_matches_interpreter() {
+        check(Target::Rust, "rs", |src, exe| {
+            let mut rustc = Command::new("rustc");
+            rustc
+                .args(["--edition", "2021", "-O", "-o"])
+                .arg(exe)
+                .arg(src);
+            rustc
+        });
+    }
+
+    #[test]
+    fn c_matches_interpreter() {
+        check(Target::C, "c", |src, exe| {
+            let mut cc = Command::new("cc");
+            cc.args(["-O2", "-o"]).arg(exe).arg(src);
+            cc
+        });
+    }
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            transpile("+[>.<-]", Target::C).unwrap(),
+            format!(
+                "{C_PRELUDE}    tape[p] += 1;\n    while (tape[p]) {{\n        p += 1;\n        \
+                 putchar(tape[p]);\n        p += 65535;\n        tape[p] += 255;\n    }}\n{C_EPILOGUE}"
+            )
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! A stepping debugger for brainfuck and boolfuck.
+//!
+//! The program runs one command at a time without any of the interpreter's optimizations, so
+//! what's shown is exactly what the source says. A `#` in the source is a breakpoint: it does
+//! nothing, but [`Debugger::run`] stops when it gets there.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::brackets::{match_brackets, BracketError};
+use crate::error::{Located, Position};
+use crate::io::{BitReader, BitWriter};
+use crate::tape::{DenseTape, Tape};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Wrapping byte cells, reading past the end of input is an error
+    Brainfuck,
+    Boolfuck,
+}
+
+impl Language {
+    fn is_command(self, ch: char) -> bool {
+        match self {
+            Language::Brainfuck => "+-<>,.[]".contains(ch),
+            Language::Boolfuck => "+,;<>[]".contains(ch),
+        }
+    }
+}
+
+/// Where a program stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Stopped after the requested number of steps
+    Paused,
+    /// Stopped at a `#`
+    Breakpoint,
+    Halted,
+    /// Brainfuck read past the end of its input
+    InputExhausted,
+}
+
+enum Memory {
+    Bytes {
+        tape: DenseTape<u8>,
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    },
+    Bits {
+        tape: DenseTape<bool>,
+        input: VecDeque<bool>,
+        output: Vec<bool>,
+    },
+}
+
+pub struct Debugger {
+    language: Language,
+    code: Vec<char>,
+    jumps: Vec<usize>,
+    /// Index of the next command or breakpoint
+    pc: usize,
+    /// Head position relative to where it started
+    head: isize,
+    memory: Memory,
+    steps: usize,
+    state: State,
+}
+
+/// Cells shown on either side of the head
+const TAPE_RADIUS: isize = 8;
+/// Source characters shown on either side of the current one
+const CODE_RADIUS: usize = 32;
+
+fn escape(bytes: &[u8]) -> String {
+    bytes.escape_ascii().to_string()
+}
+
+fn bits(bits: impl IntoIterator<Item = bool>) -> String {
+    bits.into_iter()
+        .map(|bit| if bit { '1' } else { '0' })
+        .collect()
+}
+
+impl Debugger {
+    pub fn new(
+        language: Language,
+        code: &str,
+        input: Vec<u8>,
+    ) -> Result<Self, Located<BracketError>> {
+        let chars: Vec<_> = code.chars().collect();
+        let jumps = match_brackets(&chars).map_err(|error| Located {
+            error,
+            position: Position::of(code, error.index()),
+        })?;
+        let memory = match language {
+            Language::Brainfuck => Memory::Bytes {
+                tape: DenseTape::default(),
+                input: input.into(),
+                output: vec![],
+            },
+            Language::Boolfuck => Memory::Bits {
+                tape: DenseTape::default(),
+                input: BitReader::new(&input[..])
+                    .collect::<Result<_, _>>()
+                    .expect("reading a slice can't fail"),
+                output: vec![],
+            },
+        };
+        let mut debugger = Debugger {
+            language,
+            code: chars,
+            jumps,
+            pc: 0,
+            head: 0,
+            memory,
+            steps: 0,
+            state: State::Paused,
+        };
+        debugger.skip_comments();
+        Ok(debugger)
+    }
+
+    /// Moves `pc` to the next command or breakpoint
+    fn skip_comments(&mut self) {
+        while self
+            .code
+            .get(self.pc)
+            .is_some_and(|&ch| ch != '#' && !self.language.is_command(ch))
+        {
+            self.pc += 1;
+        }
+        if self.pc == self.code.len() {
+            self.state = State::Halted;
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Commands executed so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Output so far; boolfuck's is padded to whole bytes
+    pub fn output(&self) -> Vec<u8> {
+        match &self.memory {
+            Memory::Bytes { output, .. } => output.clone(),
+            Memory::Bits { output, .. } => {
+                let mut writer = BitWriter::default();
+                for &bit in output {
+                    writer.push(bit).expect("writing to a Vec can't fail");
+                }
+                writer.finish().expect("writing to a Vec can't fail");
+                writer.into_inner()
+            }
+        }
+    }
+
+    /// Executes one command, stepping over a breakpoint first if it's on one
+    pub fn step(&mut self) -> State {
+        if matches!(self.state, State::Halted | State::InputExhausted) {
+            return self.state;
+        }
+        if self.code[self.pc] == '#' {
+            self.pc += 1;
+            self.skip_comments();
+            if self.state == State::Halted {
+                return self.state;
+            }
+        }
+        let ch = self.code[self.pc];
+        let infinite = "the tape is infinite";
+        let zero = match &mut self.memory {
+            Memory::Bytes { tape, .. } => tape.get() == 0,
+            Memory::Bits { tape, .. } => !tape.get(),
+        };
+        match (&mut self.memory, ch) {
+            (Memory::Bytes { tape, .. }, '+') => {
+                let cell = tape.current();
+                *cell = cell.wrapping_add(1);
+            }
+            (Memory::Bytes { tape, .. }, '-') => {
+                let cell = tape.current();
+                *cell = cell.wrapping_sub(1);
+            }
+            (Memory::Bits { tape, .. }, '+') => {
+                let bit = tape.current();
+                *bit = !*bit;
+            }
+            (Memory::Bytes { tape, .. }, '>') => tape.shift(1).expect(infinite),
+            (Memory::Bits { tape, .. }, '>') => tape.shift(1).expect(infinite),
+            (Memory::Bytes { tape, .. }, '<') => tape.shift(-1).expect(infinite),
+            (Memory::Bits { tape, .. }, '<') => tape.shift(-1).expect(infinite),
+            (Memory::Bytes { tape, output, .. }, '.') => output.push(tape.get()),
+            (Memory::Bits { tape, output, .. }, ';') => output.push(tape.get()),
+            (Memory::Bytes { tape, input, .. }, ',') => match input.pop_front() {
+                Some(byte) => tape.set(byte),
+                None => {
+                    self.state = State::InputExhausted;
+                    return self.state;
+                }
+            },
+            (Memory::Bits { tape, input, .. }, ',') => tape.set(input.pop_front().unwrap_or(false)),
+            (_, '[') if zero => self.pc = self.jumps[self.pc],
+            (_, ']') if !zero => self.pc = self.jumps[self.pc],
+            _ => {}
+        }
+        match ch {
+            '>' => self.head += 1,
+            '<' => self.head -= 1,
+            _ => {}
+        }
+        self.steps += 1;
+        self.pc += 1;
+        self.state = State::Paused;
+        self.skip_comments();
+        if self.state == State::Paused && self.code[self.pc] == '#' {
+            self.state = State::Breakpoint;
+        }
+        self.state
+    }
+
+    /// Steps `limit` times, or until a breakpoint if there's no limit. Stops early when the
+    /// program ends; a limit of zero doesn't step at all.
+    pub fn run(&mut self, limit: Option<usize>) -> State {
+        let mut taken = 0;
+        loop {
+            if limit == Some(taken) {
+                return self.state;
+            }
+            let state = self.step();
+            taken += 1;
+            match (state, limit) {
+                (State::Halted | State::InputExhausted, _) => return state,
+                (State::Breakpoint, None) => return state,
+                _ => {}
+            }
+        }
+    }
+
+    /// The current source line with the next command marked, a window of the tape around the
+    /// head, the input left and the output so far. With `color` the command is highlighted
+    /// with ANSI escapes instead of being marked by a caret.
+    pub fn render(&mut self, color: bool) -> String {
+        let mut out = String::new();
+
+        // the source line around pc
+        let start = self.code[..self.pc]
+            .iter()
+            .rposition(|&ch| ch == '\n')
+            .map_or(0, |i| i + 1);
+        let end = self.code[self.pc..]
+            .iter()
+            .position(|&ch| ch == '\n')
+            .map_or(self.code.len(), |i| self.pc + i);
+        let from = start.max(self.pc.saturating_sub(CODE_RADIUS));
+        let to = end.min(self.pc + CODE_RADIUS + 1);
+        let position = Position::of(&self.code.iter().collect::<String>(), self.pc);
+        let state = match self.state {
+            State::Paused => "paused",
+            State::Breakpoint => "breakpoint",
+            State::Halted => "halted",
+            State::InputExhausted => "read past the end of input",
+        };
+        let _ = writeln!(out, "{position} after {} steps, {state}", self.steps);
+        let line: String = self.code[from..to].iter().collect();
+        if self.pc >= to {
+            let _ = writeln!(out, "  {line}");
+        } else if color {
+            let before: String = self.code[from..self.pc].iter().collect();
+            let after: String = self.code[self.pc + 1..to].iter().collect();
+            let _ = writeln!(out, "  {before}\x1b[7m{}\x1b[0m{after}", self.code[self.pc]);
+        } else {
+            let _ = writeln!(out, "  {line}\n  {}^", " ".repeat(self.pc - from));
+        }
+
+        // the tape, the head's cell in brackets
+        let _ = write!(out, "tape @{}:", self.head);
+        match &mut self.memory {
+            Memory::Bytes { tape, .. } => {
+                for offset in -TAPE_RADIUS..=TAPE_RADIUS {
+                    let cell = *tape.at(offset).expect("the tape is infinite");
+                    let _ = match offset {
+                        0 => write!(out, " [{cell:3}]"),
+                        _ => write!(out, " {cell:3}"),
+                    };
+                }
+            }
+            Memory::Bits { tape, .. } => {
+                out.push(' ');
+                for offset in -TAPE_RADIUS * 2..=TAPE_RADIUS * 2 {
+                    let bit = bits([*tape.at(offset).expect("the tape is infinite")]);
+                    let _ = match offset {
+                        0 => write!(out, "[{bit}]"),
+                        _ => write!(out, "{bit}"),
+                    };
+                }
+            }
+        }
+        out.push('\n');
+
+        match &self.memory {
+            Memory::Bytes { input, output, .. } => {
+                let input: Vec<_> = input.iter().copied().collect();
+                let _ = writeln!(out, "input:  \"{}\"", escape(&input));
+                let _ = writeln!(out, "output: \"{}\"", escape(output));
+            }
+            Memory::Bits { input, output, .. } => {
+                let _ = writeln!(out, "input:  {}", bits(input.iter().copied()));
+                let whole = output.len() / 8 * 8;
+                let _ = writeln!(
+                    out,
+                    "output: \"{}\" + {}",
+                    escape(&self.output()[..whole / 8]),
+                    bits(output[whole..].iter().copied())
+                );
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_and_breakpoints() {
+        let mut debugger =
+            Debugger::new(Language::Brainfuck, "++ # >+[-]\n#,.", vec![b'x']).unwrap();
+        assert_eq!(debugger.step(), State::Paused);
+        assert_eq!(debugger.run(None), State::Breakpoint);
+        assert_eq!(debugger.steps(), 2);
+        assert_eq!(debugger.run(Some(3)), State::Paused);
+        assert_eq!(debugger.steps(), 5);
+        assert_eq!(debugger.run(Some(0)), State::Paused);
+        assert_eq!(debugger.steps(), 5);
+        assert_eq!(debugger.run(None), State::Breakpoint);
+        assert_eq!(debugger.steps(), 7);
+        assert_eq!(debugger.run(None), State::Halted);
+        assert_eq!(debugger.output(), b"x");
+        assert_eq!(debugger.step(), State::Halted);
+    }
+
+    #[test]
+    fn input_exhausted() {
+        let mut debugger = Debugger::new(Language::Brainfuck, ",,", vec![1]).unwrap();
+        assert_eq!(debugger.run(None), State::InputExhausted);
+        assert_eq!(debugger.steps(), 1);
+    }
+
+    #[test]
+    fn render_brainfuck() {
+        let mut debugger =
+            Debugger::new(Language::Brainfuck, "+++>++.<\n,.", b"ab".to_vec()).unwrap();
+        debugger.run(Some(7));
+        assert_eq!(
+            debugger.render(false),
+            "\
+1:8 after 7 steps, paused
+  +++>++.<
+         ^
+tape @1:   0   0   0   0   0   0   0   3 [  2]   0   0   0   0   0   0   0   0
+input:  \"ab\"
+output: \"\\x02\"
+"
+        );
+        debugger.run(Some(1));
+        assert_eq!(
+            debugger.render(true).lines().nth(1),
+            Some("  \x1b[7m,\x1b[0m.")
+        );
+    }
+
+    #[test]
+    fn render_boolfuck() {
+        let mut debugger = Debugger::new(Language::Boolfuck, ",;>,;+;", vec![0b01]).unwrap();
+        assert_eq!(debugger.run(None), State::Halted);
+        assert_eq!(
+            debugger.render(false),
+            "\
+1:8 after 7 steps, halted
+  ,;>,;+;
+tape @1: 0000000000000001[1]0000000000000000
+input:  000000
+output: \"\" + 101
+"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Source positions for errors.
+
+use std::fmt;
+
+/// 1-based line and column of a character in a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const START: Position = Position { line: 1, column: 1 };
+
+    /// The position of the character following one at `self`
+    pub fn after(self, ch: char) -> Position {
+        match ch {
+            '\n' => Position {
+                line: self.line + 1,
+                column: 1,
+            },
+            _ => Position {
+                column: self.column + 1,
+                ..self
+            },
+        }
+    }
+
+    /// Position of the `index`th character of `source`
+    pub fn of(source: &str, index: usize) -> Position {
+        source
+            .chars()
+            .take(index)
+            .fold(Position::START, Position::after)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An error along with where in the source it happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Located<E> {
+    pub error: E,
+    pub position: Position,
+}
+
+impl<E: fmt::Display> fmt::Display for Located<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.error)
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for Located<E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_and_columns() {
+        let source = "ab\ncd\n\ne";
+        let at = |index| {
+            let Position { line, column } = Position::of(source, index);
+            (line, column)
+        };
+        assert_eq!(at(0), (1, 1));
+        assert_eq!(at(2), (1, 3));
+        assert_eq!(at(4), (2, 2));
+        assert_eq!(at(6), (3, 1));
+        assert_eq!(at(7), (4, 1));
+    }
+}
@@ -0,0 +1,139 @@
+//! Byte and bit streams for program I/O.
+//!
+//! Programs read from any [`BufRead`] and write to any [`Write`], so they can run interactively
+//! against stdin and stdout as well as on buffers. Byte-oriented languages use the streams as
+//! they are. Bit-oriented ones read through [`BitReader`] and write through [`BitWriter`], both
+//! of which order the bits of a byte little-endian: the least significant bit comes first.
+
+use std::io::{self, BufRead, Bytes, Write};
+
+/// The bits of a byte stream, least significant first
+#[derive(Debug)]
+pub struct BitReader<R> {
+    bytes: Bytes<R>,
+    byte: u8,
+    left: u8,
+}
+
+impl<R: BufRead> BitReader<R> {
+    pub fn new(inner: R) -> Self {
+        BitReader {
+            bytes: inner.bytes(),
+            byte: 0,
+            left: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for BitReader<R> {
+    type Item = io::Result<bool>;
+
+    fn next(&mut self) -> Option<io::Result<bool>> {
+        if self.left == 0 {
+            self.byte = match self.bytes.next()? {
+                Ok(byte) => byte,
+                Err(err) => return Some(Err(err)),
+            };
+            self.left = 8;
+        }
+        let bit = self.byte & 1 == 1;
+        self.byte >>= 1;
+        self.left -= 1;
+        Some(Ok(bit))
+    }
+}
+
+/// Packs bits into bytes, least significant first, and writes every byte as soon as it's
+/// complete
+#[derive(Debug, Clone, Default)]
+pub struct BitWriter<W = Vec<u8>> {
+    inner: W,
+    byte: u8,
+    filled: u8,
+}
+
+impl<W: Write> BitWriter<W> {
+    pub fn new(inner: W) -> Self {
+        BitWriter {
+            inner,
+            byte: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) -> io::Result<()> {
+        self.byte |= (bit as u8) << self.filled;
+        self.filled += 1;
+        if self.filled == 8 {
+            self.inner.write_all(&[self.byte])?;
+            self.byte = 0;
+            self.filled = 0;
+        }
+        Ok(())
+    }
+
+    /// Flushes the whole bytes written so far, a partial byte stays buffered
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Writes a partial last byte padded with zero bits and flushes
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.filled > 0 {
+            self.inner.write_all(&[self.byte])?;
+            self.byte = 0;
+            self.filled = 0;
+        }
+        self.inner.flush()
+    }
+
+    /// The underlying writer, a partial byte that wasn't finished is dropped
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn little_endian() {
+        let bits: Vec<_> = BitReader::new(&[0b1000_0101, 0xff][..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            bits[..8],
+            [true, false, true, false, false, false, false, true]
+        );
+        assert_eq!(bits.len(), 16);
+        let mut writer = BitWriter::new(vec![]);
+        for bit in bits {
+            writer.push(bit).unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(writer.into_inner(), [0b1000_0101, 0xff]);
+    }
+
+    #[test]
+    fn pads_last_byte() {
+        let mut writer = BitWriter::new(vec![]);
+        for bit in [true, true, false, true] {
+            writer.push(bit).unwrap();
+        }
+        assert_eq!(writer.clone().into_inner(), []);
+        writer.finish().unwrap();
+        assert_eq!(writer.into_inner(), [0b1011]);
+    }
+
+    #[test]
+    fn writes_whole_bytes_only() {
+        let mut out = vec![];
+        let mut writer = BitWriter::new(&mut out);
+        for bit in (0..12).map(|i| i % 3 == 0) {
+            writer.push(bit).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(out, [0b0100_1001]);
+    }
+}
@@ -0,0 +1,28 @@
+//! Runtime shared by the esolang katas.
+//!
+//! Interpreters match their loops with [`brackets`], keep their memory on a [`tape`] and stream
+//! their I/O through [`io`]. Errors point into the source with an [`error::Position`], and
+//! [`limits`] stops programs that don't halt. [`brainfuck`] and [`boolfuck`] are built from
+//! these parts, and [`translate`] converts between the two. The [`debugger`] steps through
+//! either of them.
+//!
+//! The rest of the family is small enough to need only some of the parts: [`ministringfuck`]
+//! and [`tick`] just print, [`smallfuck`] edits a tape it's given and [`paintfuck`] draws on a
+//! grid.
+
+pub mod boolfuck;
+pub mod brackets;
+pub mod brainfuck;
+pub mod debugger;
+pub mod error;
+pub mod io;
+pub mod limits;
+pub mod ministringfuck;
+pub mod paintfuck;
+pub mod smallfuck;
+pub mod tape;
+pub mod tick;
+pub mod translate;
+
+#[cfg(test)]
+mod test_rng;
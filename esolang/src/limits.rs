@@ -0,0 +1,119 @@
+//! Stopping programs that run for too long.
+//!
+//! A run can be given a number of steps and a deadline it has to finish within. What counts as
+//! a step is up to the interpreter. The deadline is only checked every so many steps, so a
+//! program can overrun it by a few microseconds.
+
+use std::fmt;
+use std::time::Instant;
+
+use crate::error::Located;
+
+/// How far a program may run, unlimited by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    pub max_steps: Option<u64>,
+    pub deadline: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Steps,
+    Deadline,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LimitExceeded::Steps => "step limit exceeded",
+            LimitExceeded::Deadline => "deadline exceeded",
+        })
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Everything a run produced, including the output written before it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome<E, W = Vec<u8>> {
+    pub output: W,
+    /// Steps taken before the program halted or failed
+    pub steps: u64,
+    pub result: Result<(), Located<E>>,
+}
+
+impl<E, W> Outcome<E, W> {
+    /// The output if the program halted, the error otherwise
+    pub fn into_result(self) -> Result<W, Located<E>> {
+        self.result.map(|()| self.output)
+    }
+}
+
+/// Steps between two looks at the clock
+const CLOCK_INTERVAL: u64 = 1 << 12;
+
+/// Counts the steps of a run against its limits
+pub(crate) struct Budget<'a> {
+    limits: &'a Limits,
+    pub(crate) steps: u64,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(limits: &'a Limits) -> Self {
+        Budget { limits, steps: 0 }
+    }
+
+    /// Takes a step if the limits allow it
+    #[inline]
+    pub(crate) fn step(&mut self) -> Result<(), LimitExceeded> {
+        if self.limits.max_steps == Some(self.steps) {
+            return Err(LimitExceeded::Steps);
+        }
+        if self.steps.is_multiple_of(CLOCK_INTERVAL)
+            && self
+                .limits
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(LimitExceeded::Deadline);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn budget() {
+        let limits = Limits {
+            max_steps: Some(3),
+            ..Limits::default()
+        };
+        let mut budget = Budget::new(&limits);
+        assert_eq!(
+            (0..5).map(|_| budget.step()).collect::<Vec<_>>(),
+            [
+                Ok(()),
+                Ok(()),
+                Ok(()),
+                Err(LimitExceeded::Steps),
+                Err(LimitExceeded::Steps)
+            ]
+        );
+        assert_eq!(budget.steps, 3);
+
+        let limits = Limits {
+            deadline: Some(Instant::now() + Duration::from_millis(20)),
+            ..Limits::default()
+        };
+        let mut budget = Budget::new(&limits);
+        while budget.step().is_ok() {}
+        assert!(Instant::now() >= limits.deadline.unwrap());
+        assert_eq!(budget.steps % CLOCK_INTERVAL, 0);
+    }
+}
@@ -0,0 +1,28 @@
+//! MiniStringFuck: a single byte cell that `+` increments, wrapping from 255 to 0, and `.`
+//! outputs. Every other character is a comment.
+
+/// The bytes `code` outputs
+pub fn run(code: &str) -> Vec<u8> {
+    let mut cell: u8 = 0;
+    let mut output = vec![];
+    for ch in code.chars() {
+        match ch {
+            '+' => cell = cell.wrapping_add(1),
+            '.' => output.push(cell),
+            _ => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_and_ignores_comments() {
+        let code = format!("+.a+.{}. comment", "+".repeat(254));
+        assert_eq!(run(&code), [1, 2, 0]);
+        assert_eq!(run(""), []);
+    }
+}
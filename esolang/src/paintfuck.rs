@@ -0,0 +1,110 @@
+//! Paintfuck: smallfuck on a toroidal grid of bits. `n`, `e`, `s` and `w` move the head,
+//! wrapping around the edges, and `*` flips the bit under it. Programs only run for a given
+//! number of iterations, each command executed counts as one, including `[` and `]` whether
+//! they jump or not.
+
+use std::fmt;
+
+use crate::brackets::{match_brackets, BracketError};
+use crate::error::{Located, Position};
+
+/// A grid of bits, drawn as rows of `0`s and `1`s separated by CRLF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    cells: Vec<bool>,
+}
+
+impl Grid {
+    /// # Panics
+    ///
+    /// If the grid would have no cells.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "a grid needs at least one cell");
+        Grid {
+            width,
+            cells: vec![false; width * height],
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.cells.chunks(self.width)
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                f.write_str("\r\n")?;
+            }
+            for &bit in row {
+                f.write_str(if bit { "1" } else { "0" })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The grid after running `code` for at most `iterations` commands on a blank `width` by
+/// `height` one, with the head starting in the top left corner. Characters other than the
+/// commands are ignored and don't count as iterations.
+pub fn try_run(
+    code: &str,
+    iterations: usize,
+    width: usize,
+    height: usize,
+) -> Result<Grid, Located<BracketError>> {
+    let instructions: Vec<_> = code.chars().collect();
+    let jumps = match_brackets(&instructions).map_err(|error| Located {
+        error,
+        position: Position::of(code, error.index()),
+    })?;
+    let mut grid = Grid::new(width, height);
+    let (mut x, mut y) = (0, 0);
+    let mut pc = 0;
+    let mut left = iterations;
+    while pc < instructions.len() && left > 0 {
+        let bit = &mut grid.cells[y * width + x];
+        match instructions[pc] {
+            'n' => y = (y + height - 1) % height,
+            's' => y = (y + 1) % height,
+            'w' => x = (x + width - 1) % width,
+            'e' => x = (x + 1) % width,
+            '*' => *bit = !*bit,
+            '[' if !*bit => pc = jumps[pc],
+            ']' if *bit => pc = jumps[pc],
+            '[' | ']' => {}
+            _ => {
+                pc += 1;
+                continue;
+            }
+        }
+        left -= 1;
+        pc += 1;
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_around() {
+        let grid = try_run("*w*n*ee*", 100, 3, 2).unwrap();
+        assert_eq!(grid.to_string(), "101\r\n011");
+    }
+
+    #[test]
+    fn counts_iterations() {
+        let run = |code, iterations| try_run(code, iterations, 3, 1).unwrap().to_string();
+        // comments are free
+        assert_eq!(run("e * flip", 2), "010");
+        assert_eq!(run("*[e]", 5), "100");
+        // `]` counts as an iteration when it jumps back
+        assert_eq!(run("*[*e*]", 5), "010");
+        assert_eq!(run("*[*e*]", 7), "000");
+        assert_eq!(run("*[*e*]", 9), "001");
+    }
+}
@@ -0,0 +1,75 @@
+//! Smallfuck: boolfuck without I/O on a finite tape of bits given up front. `*` flips the
+//! current bit. The program ends when it runs out of commands or the head moves off either end
+//! of the tape.
+
+use crate::brackets::{match_brackets, BracketError};
+use crate::error::{Located, Position};
+use crate::tape::{DenseTape, Tape};
+
+/// The tape after running `code` on it. Characters other than the commands are ignored.
+///
+/// # Panics
+///
+/// If `tape` is empty.
+pub fn try_run(code: &str, tape: Vec<bool>) -> Result<Vec<bool>, Located<BracketError>> {
+    let instructions: Vec<_> = code.chars().collect();
+    let jumps = match_brackets(&instructions).map_err(|error| Located {
+        error,
+        position: Position::of(code, error.index()),
+    })?;
+    let mut tape = DenseTape::bounded(tape);
+    let mut pc = 0;
+    while pc < instructions.len() {
+        let moved = match instructions[pc] {
+            '>' => tape.shift(1),
+            '<' => tape.shift(-1),
+            '*' => {
+                let bit = tape.current();
+                *bit = !*bit;
+                Ok(())
+            }
+            '[' if !tape.get() => {
+                pc = jumps[pc];
+                Ok(())
+            }
+            ']' if tape.get() => {
+                pc = jumps[pc];
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if moved.is_err() {
+            break;
+        }
+        pc += 1;
+    }
+    Ok(tape.cells().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(s: &str) -> Vec<bool> {
+        s.chars().map(|ch| ch == '1').collect()
+    }
+
+    #[test]
+    fn stops_at_the_edges() {
+        assert_eq!(try_run(">*>*>*", bits("000")), Ok(bits("011")));
+        assert_eq!(try_run("*<*", bits("000")), Ok(bits("100")));
+        // the loop moves right until it falls off the end
+        assert_eq!(try_run("*[>*]", bits("0000")), Ok(bits("1111")));
+    }
+
+    #[test]
+    fn unmatched_brackets() {
+        assert_eq!(
+            try_run("*\n]", bits("0")),
+            Err(Located {
+                error: BracketError::UnmatchedClose(2),
+                position: Position { line: 2, column: 1 },
+            })
+        );
+    }
+}
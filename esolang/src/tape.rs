@@ -0,0 +1,316 @@
+//! Tapes of cells under a movable head.
+//!
+//! [`SparseTape`] only stores the cells that were touched, which suits programs that jump
+//! around a huge tape. [`DenseTape`] keeps a contiguous `Vec` and is the faster choice for
+//! everything else. [`BitTape`] is a dense tape of single bits packed 64 to a word.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::repeat_n;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TapeModel {
+    /// Grows in both directions as needed
+    #[default]
+    Infinite,
+    /// `n` cells with the pointer wrapping around at either end
+    Wrap(usize),
+    /// `n` cells, moving past either end is an error
+    Bounded(usize),
+}
+
+impl TapeModel {
+    /// Where the head ends up moving `by` cells from `pos`
+    fn target(self, pos: isize, by: isize) -> Result<isize, OutOfBounds> {
+        match self {
+            TapeModel::Infinite => Ok(pos + by),
+            TapeModel::Wrap(len) => Ok((pos + by).rem_euclid(len as isize)),
+            TapeModel::Bounded(len) if (0..len as isize).contains(&(pos + by)) => Ok(pos + by),
+            TapeModel::Bounded(_) => Err(OutOfBounds),
+        }
+    }
+
+    fn check(self) {
+        if let TapeModel::Wrap(0) | TapeModel::Bounded(0) = self {
+            panic!("a tape needs at least one cell");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("pointer moved off the tape")
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+pub trait Tape<Cell: Copy> {
+    /// The cell under the head
+    fn current(&mut self) -> &mut Cell;
+
+    /// The cell `offset` cells away from the head
+    fn at(&mut self, offset: isize) -> Result<&mut Cell, OutOfBounds>;
+
+    fn shift(&mut self, by: isize) -> Result<(), OutOfBounds>;
+
+    #[inline]
+    fn get(&mut self) -> Cell {
+        *self.current()
+    }
+
+    #[inline]
+    fn set(&mut self, cell: Cell) {
+        *self.current() = cell;
+    }
+}
+
+/// Cells in a `HashMap`, untouched ones read as `Cell::default()`
+#[derive(Debug, Clone, Default)]
+pub struct SparseTape<Cell> {
+    cells: HashMap<isize, Cell>,
+    head: isize,
+    model: TapeModel,
+}
+
+impl<Cell> SparseTape<Cell> {
+    pub fn new(model: TapeModel) -> Self {
+        model.check();
+        SparseTape {
+            cells: HashMap::new(),
+            head: 0,
+            model,
+        }
+    }
+}
+
+impl<Cell: Copy + Default> Tape<Cell> for SparseTape<Cell> {
+    #[inline]
+    fn current(&mut self) -> &mut Cell {
+        self.cells.entry(self.head).or_default()
+    }
+
+    #[inline]
+    fn at(&mut self, offset: isize) -> Result<&mut Cell, OutOfBounds> {
+        let pos = self.model.target(self.head, offset)?;
+        Ok(self.cells.entry(pos).or_default())
+    }
+
+    #[inline]
+    fn shift(&mut self, by: isize) -> Result<(), OutOfBounds> {
+        self.head = self.model.target(self.head, by)?;
+        Ok(())
+    }
+}
+
+/// Cells in a `Vec`. An infinite tape grows in whichever direction the head leaves it.
+#[derive(Debug, Clone)]
+pub struct DenseTape<Cell> {
+    cells: Vec<Cell>,
+    head: usize,
+    model: TapeModel,
+}
+
+impl<Cell: Copy + Default> DenseTape<Cell> {
+    pub fn new(model: TapeModel) -> Self {
+        model.check();
+        let len = match model {
+            TapeModel::Infinite => 64,
+            TapeModel::Wrap(len) | TapeModel::Bounded(len) => len,
+        };
+        DenseTape {
+            cells: vec![Cell::default(); len],
+            head: 0,
+            model,
+        }
+    }
+
+    /// A tape of exactly `cells`, moving past either end is an error. The head starts on the
+    /// first cell.
+    pub fn bounded(cells: Vec<Cell>) -> Self {
+        let model = TapeModel::Bounded(cells.len());
+        model.check();
+        DenseTape {
+            cells,
+            head: 0,
+            model,
+        }
+    }
+
+    /// Every cell the tape holds, including the ones an infinite tape has grown by
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Index of the cell `offset` away from the head, growing an infinite tape to have it
+    #[inline]
+    fn index(&mut self, offset: isize) -> Result<usize, OutOfBounds> {
+        let target = self.model.target(self.head as isize, offset)?;
+        let len = self.cells.len();
+        if target < 0 {
+            let grow = target.unsigned_abs().max(len);
+            self.cells.splice(0..0, repeat_n(Cell::default(), grow));
+            self.head += grow;
+            return Ok((target + grow as isize) as usize);
+        }
+        let target = target as usize;
+        if target >= len {
+            self.cells
+                .resize((target + 1).max(len * 2), Cell::default());
+        }
+        Ok(target)
+    }
+}
+
+impl<Cell: Copy + Default> Default for DenseTape<Cell> {
+    fn default() -> Self {
+        DenseTape::new(TapeModel::Infinite)
+    }
+}
+
+impl<Cell: Copy + Default> Tape<Cell> for DenseTape<Cell> {
+    #[inline]
+    fn current(&mut self) -> &mut Cell {
+        &mut self.cells[self.head]
+    }
+
+    #[inline]
+    fn at(&mut self, offset: isize) -> Result<&mut Cell, OutOfBounds> {
+        let idx = self.index(offset)?;
+        Ok(&mut self.cells[idx])
+    }
+
+    #[inline]
+    fn shift(&mut self, by: isize) -> Result<(), OutOfBounds> {
+        self.head = self.index(by)?;
+        Ok(())
+    }
+}
+
+/// An infinite tape of bits packed into `u64` words, least significant bit first. Like
+/// [`DenseTape`] it grows in whichever direction the head leaves it.
+#[derive(Debug, Clone)]
+pub struct BitTape {
+    words: Vec<u64>,
+    /// Bit index of the head in `words`
+    head: usize,
+    /// Bit index of the cell the head started on
+    origin: usize,
+}
+
+impl BitTape {
+    /// Head position relative to where it started
+    pub fn position(&self) -> isize {
+        self.head as isize - self.origin as isize
+    }
+
+    #[inline]
+    pub fn get(&self) -> bool {
+        self.words[self.head / 64] >> (self.head % 64) & 1 == 1
+    }
+
+    #[inline]
+    pub fn set(&mut self, bit: bool) {
+        let word = &mut self.words[self.head / 64];
+        *word = *word & !(1 << (self.head % 64)) | (bit as u64) << (self.head % 64);
+    }
+
+    #[inline]
+    pub fn flip(&mut self) {
+        self.words[self.head / 64] ^= 1 << (self.head % 64);
+    }
+
+    #[inline]
+    pub fn shift(&mut self, by: isize) {
+        let target = self.head as isize + by;
+        let len = self.words.len();
+        if target < 0 {
+            let grow = target.unsigned_abs().div_ceil(64).max(len);
+            self.words.splice(0..0, repeat_n(0, grow));
+            self.head = (target + grow as isize * 64) as usize;
+            self.origin += grow * 64;
+            return;
+        }
+        self.head = target as usize;
+        if self.head / 64 >= len {
+            self.words.resize((self.head / 64 + 1).max(len * 2), 0);
+        }
+    }
+}
+
+impl Default for BitTape {
+    fn default() -> Self {
+        BitTape {
+            words: vec![0; 4],
+            head: 0,
+            origin: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the position into every cell on the way and reads it back
+    fn walk(tape: &mut impl Tape<i32>) -> Result<Vec<i32>, OutOfBounds> {
+        for pos in 0..5 {
+            tape.set(pos);
+            tape.shift(1)?;
+        }
+        tape.shift(-8)?;
+        *tape.at(2)? += 10;
+        let mut seen = vec![];
+        for _ in 0..8 {
+            seen.push(tape.get());
+            tape.shift(1)?;
+        }
+        Ok(seen)
+    }
+
+    #[test]
+    fn models() {
+        for (model, expected) in [
+            (TapeModel::Infinite, Ok(vec![0, 0, 10, 0, 1, 2, 3, 4])),
+            (TapeModel::Wrap(6), Ok(vec![3, 4, 10, 0, 1, 2, 3, 4])),
+            (TapeModel::Bounded(6), Err(OutOfBounds)),
+        ] {
+            assert_eq!(walk(&mut SparseTape::new(model)), expected, "{model:?}");
+            assert_eq!(walk(&mut DenseTape::new(model)), expected, "{model:?}");
+        }
+    }
+
+    #[test]
+    fn dense_grows_left() {
+        let mut tape = DenseTape::default();
+        tape.shift(-1000).unwrap();
+        tape.set(7u8);
+        tape.shift(1000).unwrap();
+        assert_eq!(*tape.at(-1000).unwrap(), 7);
+        assert_eq!(tape.get(), 0);
+    }
+
+    #[test]
+    fn bits_grow_both_ways() {
+        let mut tape = BitTape::default();
+        for by in [-1, -200, 1000, 63, 1, -1000, 137] {
+            tape.shift(by);
+            tape.flip();
+        }
+        assert_eq!(tape.position(), 0);
+        assert!(tape.get());
+        tape.set(false);
+        let mut set = vec![];
+        tape.shift(-300);
+        for _ in 0..1400 {
+            if tape.get() {
+                set.push(tape.position());
+            }
+            tape.shift(1);
+        }
+        assert_eq!(set, [-201, -137, -1, 799, 862, 863]);
+    }
+}
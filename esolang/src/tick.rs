@@ -0,0 +1,35 @@
+//! Tick: brainfuck without loops or input. `>` and `<` move along an infinite tape of bytes,
+//! `+` increments the current one, wrapping from 255 to 0, and `*` outputs it. Every other
+//! character is a comment.
+
+use crate::tape::{DenseTape, Tape};
+
+/// The bytes `code` outputs
+pub fn run(code: &str) -> Vec<u8> {
+    let mut tape: DenseTape<u8> = DenseTape::default();
+    let mut output = vec![];
+    for ch in code.chars() {
+        match ch {
+            '>' => tape.shift(1).expect("the tape is infinite"),
+            '<' => tape.shift(-1).expect("the tape is infinite"),
+            '+' => {
+                let cell = tape.current();
+                *cell = cell.wrapping_add(1);
+            }
+            '*' => output.push(tape.get()),
+            _ => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_both_ways() {
+        let code = format!("<<+*>>>++*<<<* wrap: {}*", "+".repeat(255));
+        assert_eq!(run(&code), [1, 2, 1, 0]);
+    }
+}
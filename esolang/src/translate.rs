@@ -0,0 +1,129 @@
+//! Translation between brainfuck and boolfuck.
+//!
+//! A brainfuck cell becomes nine boolfuck bits: a scratch bit used by the carry loops followed
+//! by the eight bits of the value, least significant first, which is also the order boolfuck
+//! does I/O in. Every command expands to a fixed snippet, so a boolfuck program made of these
+//! snippets translates back.
+//!
+//! Brainfuck reading past the end of input has to set the cell to zero, which is what the
+//! boolfuck side does with the zero bits it reads there.
+
+const EXPANSIONS: [(char, &str); 8] = [
+    ('+', ">[>]+<[+<]>>>>>>>>>[+]<<<<<<<<<"),
+    ('-', ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+]<<<<<<<<<"),
+    ('<', "<<<<<<<<<"),
+    ('>', ">>>>>>>>>"),
+    (',', ">,>,>,>,>,>,>,>,<<<<<<<<"),
+    ('.', ">;>;>;>;>;>;>;>;<<<<<<<<"),
+    (
+        '[',
+        ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>[+<<<<<<<<[>]+<[+<]",
+    ),
+    (']', ">>>>>>>>>+<<<<<<<<+[>+]<[<]>>>>>>>>>]<[+<]"),
+];
+
+/// Boolfuck doing the same as the brainfuck `code`, comments are dropped
+pub fn brainfuck_to_boolfuck(code: &str) -> String {
+    code.chars()
+        .filter_map(|ch| EXPANSIONS.iter().find(|(command, _)| *command == ch))
+        .map(|(_, expansion)| *expansion)
+        .collect()
+}
+
+/// The brainfuck a boolfuck program was translated from, if it's made of the snippets
+/// [`brainfuck_to_boolfuck`] emits. Non-command characters are ignored.
+pub fn boolfuck_to_brainfuck(code: &str) -> Option<String> {
+    let code: String = code.chars().filter(|ch| "+,;<>[]".contains(*ch)).collect();
+    let mut rest = code.as_str();
+    let mut brainfuck = String::new();
+    while !rest.is_empty() {
+        // `>` is a prefix of several snippets, so the longest match wins
+        let (command, expansion) = EXPANSIONS
+            .iter()
+            .filter(|(_, expansion)| rest.starts_with(expansion))
+            .max_by_key(|(_, expansion)| expansion.len())?;
+        brainfuck.push(*command);
+        rest = &rest[expansion.len()..];
+    }
+    Some(brainfuck)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boolfuck;
+    use crate::brainfuck::{self, BfConfig, Eof};
+    use crate::test_rng::Rng;
+
+    /// A random program that always halts. Loops decrement their counter once per iteration
+    /// and their bodies stay strictly to the right of it.
+    fn program(rng: &mut Rng, len: usize, depth: usize, top: bool) -> String {
+        let mut code = String::new();
+        let mut pos: isize = 0;
+        for _ in 0..len {
+            match rng.below(if depth > 0 { 8 } else { 7 }) {
+                0 | 1 => code.push('+'),
+                2 => code.push('-'),
+                3 => code.push('.'),
+                4 => code.push(','),
+                5 => {
+                    code.push('>');
+                    pos += 1;
+                }
+                6 if top || pos > 0 => {
+                    code.push('<');
+                    pos -= 1;
+                }
+                6 => {}
+                _ => {
+                    let body = program(rng, len / 2, depth - 1, false);
+                    code.push_str(&format!("[->{body}<]"));
+                }
+            }
+        }
+        if !top {
+            code.push_str(&"<".repeat(pos as usize));
+        }
+        code
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(
+            brainfuck_to_boolfuck("+ comment >"),
+            format!("{}{}", EXPANSIONS[0].1, ">".repeat(9))
+        );
+        assert_eq!(boolfuck_to_brainfuck(";"), None);
+        assert_eq!(boolfuck_to_brainfuck(">>>>"), None);
+        assert_eq!(boolfuck_to_brainfuck(""), Some(String::new()));
+    }
+
+    #[test]
+    fn hello_world() {
+        let code = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        assert_eq!(
+            boolfuck::try_run(&brainfuck_to_boolfuck(code), vec![]),
+            Ok(b"Hello World!\n".to_vec())
+        );
+    }
+
+    #[test]
+    fn same_output_both_ways() {
+        let config = BfConfig {
+            eof: Eof::Zero,
+            ..Default::default()
+        };
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let code = program(&mut rng, 12, 2, true);
+            let input: Vec<u8> = (0..rng.below(4)).map(|_| rng.below(256) as u8).collect();
+            let translated = brainfuck_to_boolfuck(&code);
+            assert_eq!(
+                boolfuck::try_run(&translated, input.clone()),
+                Ok(brainfuck::try_run(&code, input, &config).unwrap()),
+                "{code}"
+            );
+            assert_eq!(boolfuck_to_brainfuck(&translated), Some(code));
+        }
+    }
+}
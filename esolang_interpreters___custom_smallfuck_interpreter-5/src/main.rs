@@ -0,0 +1,34 @@
+fn interpreter(code: &str, tape: &str) -> String {
+    let tape = tape.chars().map(|ch| ch == '1').collect();
+    esolang::smallfuck::try_run(code, tape)
+        .unwrap_or_else(|err| panic!("{err}"))
+        .into_iter()
+        .map(|bit| if bit { '1' } else { '0' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_test_cases() {
+        // Flips the leftmost cell of the tape
+        assert_eq!(interpreter("*", "00101100"), "10101100");
+        // Flips the second and third cell of the tape
+        assert_eq!(interpreter(">*>*", "00101100"), "01001100");
+        // Flips all the bits in the tape
+        assert_eq!(interpreter("*>*>*>*>*>*>*>*", "00101100"), "11010011");
+        // Flips all the bits that are initialized to 0
+        assert_eq!(interpreter("*>*>>*>>>*>*", "00101100"), "11111111");
+        // Goes somewhere to the right of the tape and then flips all bits that are initialized to 1, progressing leftwards through the tape
+        assert_eq!(interpreter(">>>>>*<*<<*", "00101100"), "00000000");
+    }
+
+    #[test]
+    fn ends_off_the_tape() {
+        // Moves right until it falls off the end, ignoring the comment and the flip after the loop
+        assert_eq!(interpreter("*[>*] end *", "0000"), "1111");
+        assert_eq!(interpreter("<*", "01"), "01");
+    }
+}
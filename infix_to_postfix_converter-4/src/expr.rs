@@ -0,0 +1,327 @@
+//! Parsed expressions and what can be done with them.
+//!
+//! Negation is written `~` in postfix and prefix notation so it can't be mistaken for
+//! subtraction, and calls are written `name/args`, like `max/3`, since postfix has nowhere
+//! else to say how many arguments they take. Expressions display as infix with only the
+//! parentheses needed to parse them back into the same tree.
+
+mod calculus;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::postfix::Number;
+use crate::shunting_yard::Rpn;
+use crate::{Op, ParseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    /// Any [`Op`] but negation
+    Binary(Op, Box<Expr>, Box<Expr>),
+    /// A function applied to its arguments
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    UnboundVariable(String),
+    /// A function the number type doesn't have or with arguments it doesn't take
+    UnknownFunction(String, usize),
+    DivisionByZero,
+    /// The result doesn't fit the number type
+    Overflow,
+    /// An exponent the number type can't raise to, like a negative one for integers
+    BadExponent,
+    /// Postfix input that isn't a valid expression
+    Syntax(ParseError),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UnboundVariable(name) => write!(f, "unbound variable `{name}`"),
+            EvalError::UnknownFunction(name, args) => write!(f, "unknown function `{name}/{args}`"),
+            EvalError::DivisionByZero => f.write_str("division by zero"),
+            EvalError::Overflow => f.write_str("overflow"),
+            EvalError::BadExponent => f.write_str("unsupported exponent"),
+            EvalError::Syntax(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<ParseError> for EvalError {
+    fn from(e: ParseError) -> Self {
+        EvalError::Syntax(e)
+    }
+}
+
+impl Expr {
+    pub fn binary(op: Op, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    /// Builds the expression that `rpn` evaluates. Prefix `-` is negation, other operators
+    /// that aren't an [`Op`] become calls named after their symbol.
+    ///
+    /// # Panics
+    ///
+    /// If `rpn` takes more values off the stack than it pushed, or leaves other than one.
+    pub fn from_rpn(rpn: Vec<Rpn>) -> Expr {
+        let op = |symbol: &str| {
+            let mut chars = symbol.chars();
+            chars
+                .next()
+                .filter(|_| chars.next().is_none())
+                .and_then(|c| Op::try_from(c).ok())
+        };
+        let mut stack = vec![];
+        for step in rpn {
+            let expr = match step {
+                Rpn::Number(n) => Expr::Number(n),
+                Rpn::Var(name) => Expr::Var(name),
+                Rpn::Prefix(symbol) => {
+                    let operand = stack.pop().expect("an operand");
+                    if symbol == "-" {
+                        Expr::Neg(Box::new(operand))
+                    } else {
+                        Expr::Call(symbol, vec![operand])
+                    }
+                }
+                Rpn::Binary(symbol) => {
+                    let rhs = stack.pop().expect("a right operand");
+                    let lhs = stack.pop().expect("a left operand");
+                    match op(&symbol) {
+                        Some(op) => Expr::binary(op, lhs, rhs),
+                        None => Expr::Call(symbol, vec![lhs, rhs]),
+                    }
+                }
+                Rpn::Call(name, args) => {
+                    let args = stack.split_off(stack.len().checked_sub(args).expect("arguments"));
+                    Expr::Call(name, args)
+                }
+            };
+            stack.push(expr);
+        }
+        assert_eq!(stack.len(), 1, "RPN should leave a single value");
+        stack.pop().expect("one value")
+    }
+
+    /// Operands and operators in postfix order
+    pub fn postfix_tokens(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        self.walk(&mut tokens, false);
+        tokens
+    }
+
+    /// Operands and operators in prefix order
+    pub fn prefix_tokens(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        self.walk(&mut tokens, true);
+        tokens
+    }
+
+    fn walk(&self, tokens: &mut Vec<String>, prefix: bool) {
+        let (op, operands) = match self {
+            Expr::Number(n) => return tokens.push(n.to_string()),
+            Expr::Var(name) => return tokens.push(name.clone()),
+            Expr::Neg(operand) => (Op::Negate.symbol().to_string(), vec![&**operand]),
+            Expr::Binary(op, lhs, rhs) => (op.symbol().to_string(), vec![&**lhs, &**rhs]),
+            Expr::Call(name, args) => (format!("{name}/{}", args.len()), args.iter().collect()),
+        };
+        if prefix {
+            tokens.push(op.clone());
+        }
+        for operand in operands {
+            operand.walk(tokens, prefix);
+        }
+        if !prefix {
+            tokens.push(op);
+        }
+    }
+
+    /// Postfix notation with the tokens separated by spaces
+    pub fn postfix(&self) -> String {
+        self.postfix_tokens().join(" ")
+    }
+
+    /// Prefix notation with the tokens separated by spaces
+    pub fn prefix(&self) -> String {
+        self.prefix_tokens().join(" ")
+    }
+
+    /// Infix notation with every operation in parentheses
+    pub fn parenthesized(&self) -> String {
+        match self {
+            Expr::Number(n) => n.to_string(),
+            Expr::Var(name) => name.clone(),
+            Expr::Neg(operand) => format!("(-{})", operand.parenthesized()),
+            Expr::Binary(op, lhs, rhs) => format!(
+                "({} {} {})",
+                lhs.parenthesized(),
+                op.symbol(),
+                rhs.parenthesized()
+            ),
+            Expr::Call(name, args) => {
+                let args: Vec<_> = args.iter().map(Expr::parenthesized).collect();
+                format!("{name}({})", args.join(", "))
+            }
+        }
+    }
+
+    /// How tightly the expression holds together when written out, atoms can't be split
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Number(n) if n.is_sign_negative() => Op::Negate.precedence(),
+            Expr::Number(_) | Expr::Var(_) | Expr::Call(..) => u8::MAX,
+            Expr::Neg(_) => Op::Negate.precedence(),
+            Expr::Binary(op, _, _) => op.precedence(),
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parenthesize: bool) -> fmt::Result {
+        if parenthesize {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+
+    /// The value of the expression with its variables taken from `env`
+    pub fn eval(&self, env: &HashMap<String, f64>) -> Result<f64, EvalError> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Var(name) => *env
+                .get(name)
+                .ok_or_else(|| EvalError::UnboundVariable(name.clone()))?,
+            Expr::Neg(operand) => -operand.eval(env)?,
+            Expr::Binary(op, lhs, rhs) => f64::apply(*op, lhs.eval(env)?, rhs.eval(env)?)?,
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(env))
+                    .collect::<Result<_, _>>()?;
+                f64::call(name, args)?
+            }
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{n}"),
+            Expr::Var(name) => f.write_str(name),
+            Expr::Call(name, args) => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                f.write_str(")")
+            }
+            Expr::Neg(operand) => {
+                f.write_str("-")?;
+                operand.fmt_operand(f, operand.precedence() < Op::Negate.precedence())
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let precedence = op.precedence();
+                lhs.fmt_operand(
+                    f,
+                    lhs.precedence() < precedence
+                        || (lhs.precedence() == precedence && !op.left_associative()),
+                )?;
+                write!(f, " {} ", op.symbol())?;
+                // a negation on the right takes everything after it that binds tighter than
+                // `op` anyway, so it never needs parentheses
+                let negation = rhs.precedence() == Op::Negate.precedence();
+                rhs.fmt_operand(
+                    f,
+                    !negation
+                        && (rhs.precedence() < precedence
+                            || (rhs.precedence() == precedence && op.left_associative())),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard::{Assoc, OpTable};
+    use crate::{parse, parse_with};
+
+    #[test]
+    fn notations() {
+        let expr = parse("-(a + 2.5) * b ^ c ^ 2").unwrap();
+        assert_eq!(expr.postfix(), "a 2.5 + ~ b c 2 ^ ^ *");
+        assert_eq!(expr.prefix(), "* ~ + a 2.5 ^ b ^ c 2");
+        assert_eq!(expr.parenthesized(), "((-(a + 2.5)) * (b ^ (c ^ 2)))");
+        assert_eq!(expr.to_string(), "-(a + 2.5) * b ^ c ^ 2");
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        for (infix, minimal) in [
+            ("(a - b) - (c - d)", "a - b - (c - d)"),
+            ("(a ^ b) ^ (c ^ d)", "(a ^ b) ^ c ^ d"),
+            ("(a * b) + (c / d)", "a * b + c / d"),
+            ("(a + b) / (c * d)", "(a + b) / (c * d)"),
+            ("(-a) ^ b", "(-a) ^ b"),
+            ("-(a ^ b)", "-a ^ b"),
+            ("-(a * b) * c", "-(a * b) * c"),
+            ("a ^ (-b) * c", "a ^ -b * c"),
+            ("a - (-(-b))", "a - --b"),
+        ] {
+            let expr = parse(infix).unwrap();
+            assert_eq!(expr.to_string(), minimal, "{infix}");
+            assert_eq!(parse(minimal), Ok(expr), "{infix}");
+        }
+        let negative = Expr::binary(Op::Exponent, Expr::Number(-2.0), Expr::Number(2.0));
+        assert_eq!(negative.to_string(), "(-2) ^ 2");
+    }
+
+    #[test]
+    fn eval() {
+        let env = HashMap::from([("x".to_string(), 3.0), ("rate".to_string(), 0.5)]);
+        let eval = |infix| parse(infix).unwrap().eval(&env);
+        assert_eq!(eval("2 * x ^ 2 - x / rate"), Ok(12.0));
+        assert_eq!(eval("-2 ^ 2"), Ok(-4.0));
+        assert_eq!(eval("2 ^ -1"), Ok(0.5));
+        assert_eq!(eval("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(eval("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(
+            eval("x * y"),
+            Err(EvalError::UnboundVariable("y".to_string()))
+        );
+        assert_eq!(eval("1 / (x - 3)"), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn calls() {
+        let expr = parse("max(x, -abs(y), 2) ^ sqrt(4)").unwrap();
+        assert_eq!(expr.to_string(), "max(x, -abs(y), 2) ^ sqrt(4)");
+        assert_eq!(expr.postfix(), "x y abs/1 ~ 2 max/3 4 sqrt/1 ^");
+        assert_eq!(expr.prefix(), "^ max/3 x ~ abs/1 y 2 sqrt/1 4");
+        assert_eq!(expr.parenthesized(), "(max(x, (-abs(y)), 2) ^ sqrt(4))");
+        let env = HashMap::from([("x".to_string(), 1.5), ("y".to_string(), -7.0)]);
+        assert_eq!(expr.eval(&env), Ok(4.0));
+
+        // operators outside of `Op` come out as calls
+        let mut table = OpTable::arithmetic();
+        table.binary("%", 2, Assoc::Left).prefix("!", 5);
+        let expr = parse_with(&table, "!x % 3 + 1").unwrap();
+        assert_eq!(expr.to_string(), "%(!(x), 3) + 1");
+        assert_eq!(
+            expr.eval(&env),
+            Err(EvalError::UnknownFunction("!".to_string(), 1))
+        );
+    }
+}
@@ -0,0 +1,713 @@
+//! Simplification and symbolic differentiation.
+//!
+//! Simplifying brings an expression to a canonical form, a sum of products where every product
+//! is a constant times powers of factors that don't break down any further. Constants are
+//! folded and gathered, like terms and like factors are combined and the rest is sorted, so
+//! `x * 2 + 3 + 1` and `4 + 2 * x` come out the same, and so do `x * y` and `y * x`. Constants
+//! lead products and trail sums, and dividing by one keeps it a fraction, like `x / 3`.
+//!
+//! The identities used, `0 * x = 0` and `x ^ 0 = 1` among them, assume every subexpression
+//! has a finite value, so `x - x` is `0` and `x / x` is `1` too. Folding leaves alone whatever
+//! doesn't evaluate to a finite number, like `1 / 0`.
+
+use std::fmt;
+
+use super::Expr;
+use crate::postfix::Number;
+use crate::Op;
+
+/// A function [`Expr::derivative`] has no rule for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoDerivative(pub String);
+
+impl fmt::Display for NoDerivative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no derivative rule for `{}`", self.0)
+    }
+}
+
+impl std::error::Error for NoDerivative {}
+
+fn number(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// A number, or a fraction of two as [`Expr::simplify`] writes them
+fn value(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Binary(Op::Divide, numer, denom) => Some(number(numer)? / number(denom)?),
+        expr => number(expr),
+    }
+}
+
+fn multiplied(factors: impl IntoIterator<Item = Expr>) -> Option<Expr> {
+    factors
+        .into_iter()
+        .reduce(|lhs, rhs| Expr::binary(Op::Multiply, lhs, rhs))
+}
+
+/// A folded constant, in lowest terms while both parts are integers and as a single `f64`
+/// once either isn't
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Fraction {
+    numer: f64,
+    /// Always positive
+    denom: f64,
+}
+
+impl Fraction {
+    const ZERO: Fraction = Fraction {
+        numer: 0.0,
+        denom: 1.0,
+    };
+    const ONE: Fraction = Fraction {
+        numer: 1.0,
+        denom: 1.0,
+    };
+
+    /// `None` if the fraction isn't finite
+    fn new(numer: f64, denom: f64) -> Option<Fraction> {
+        // integers past 2^53 aren't all representable, so there's nothing exact to reduce
+        const EXACT: f64 = (1u64 << 53) as f64;
+        if !(numer / denom).is_finite() || !denom.is_finite() {
+            return None;
+        }
+        let (numer, denom) = (numer * denom.signum(), denom.abs());
+        if numer.fract() != 0.0 || denom.fract() != 0.0 || numer.abs() >= EXACT || denom >= EXACT {
+            return Some(Fraction {
+                numer: numer / denom + 0.0,
+                denom: 1.0,
+            });
+        }
+        let (mut a, mut b) = (numer.abs() as u64, denom as u64);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        // `+ 0.0` turns a negative zero into a positive one
+        Some(Fraction {
+            numer: numer / a as f64 + 0.0,
+            denom: denom / a as f64,
+        })
+    }
+
+    fn add(self, other: Fraction) -> Option<Fraction> {
+        let numer = self.numer * other.denom + other.numer * self.denom;
+        Fraction::new(numer, self.denom * other.denom)
+    }
+
+    fn mul(self, other: Fraction) -> Option<Fraction> {
+        Fraction::new(self.numer * other.numer, self.denom * other.denom)
+    }
+
+    fn neg(self) -> Fraction {
+        Fraction {
+            numer: -self.numer + 0.0,
+            ..self
+        }
+    }
+
+    fn powi(self, exponent: i32) -> Option<Fraction> {
+        if exponent < 0 {
+            Fraction::new(self.denom, self.numer)?.powi(-exponent)
+        } else {
+            Fraction::new(self.numer.powi(exponent), self.denom.powi(exponent))
+        }
+    }
+
+    fn to_expr(self) -> Expr {
+        if self.denom == 1.0 {
+            Expr::Number(self.numer)
+        } else {
+            let (numer, denom) = (Expr::Number(self.numer), Expr::Number(self.denom));
+            Expr::binary(Op::Divide, numer, denom)
+        }
+    }
+}
+
+/// `coefficient * factors`, every factor a base that doesn't break down further raised to a
+/// constant power. Numbers only show up as bases when multiplying them in would overflow.
+#[derive(Debug, Clone, PartialEq)]
+struct Product {
+    coefficient: Fraction,
+    factors: Vec<(Expr, f64)>,
+}
+
+impl Product {
+    fn constant(n: Fraction) -> Product {
+        Product {
+            coefficient: n,
+            factors: vec![],
+        }
+    }
+
+    fn atom(base: Expr, exponent: f64) -> Product {
+        Product {
+            coefficient: Fraction::ONE,
+            factors: vec![(base, exponent)],
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// The product of `expr`, canonical
+    fn of(expr: &Expr) -> Product {
+        let product = match expr {
+            Expr::Number(n) => match Fraction::new(*n, 1.0) {
+                Some(n) => Product::constant(n),
+                None => Product::atom(Expr::Number(*n), 1.0),
+            },
+            Expr::Neg(operand) => {
+                Product::of(operand).times(Product::constant(Fraction::ONE.neg()))
+            }
+            Expr::Binary(Op::Multiply, lhs, rhs) => Product::of(lhs).times(Product::of(rhs)),
+            Expr::Binary(Op::Divide, lhs, rhs) => match Product::of(rhs).pow(-1.0) {
+                Some(inverse) => Product::of(lhs).times(inverse),
+                None => Product::atom(
+                    Expr::binary(Op::Divide, lhs.simplify(), rhs.simplify()),
+                    1.0,
+                ),
+            },
+            Expr::Binary(Op::Exponent, lhs, rhs) => {
+                let (base, exponent) = (lhs.simplify(), rhs.simplify());
+                match value(&exponent) {
+                    Some(n) => Product::of(&base).pow(n).unwrap_or_else(|| {
+                        Product::atom(Expr::binary(Op::Exponent, base, exponent), 1.0)
+                    }),
+                    None if base == Expr::Number(1.0) => Product::constant(Fraction::ONE),
+                    None => Product::atom(Expr::binary(Op::Exponent, base, exponent), 1.0),
+                }
+            }
+            Expr::Binary(Op::Plus | Op::Minus, ..) => {
+                let mut sum = Sum::of(expr);
+                match (&sum.terms[..], &sum.constants[..]) {
+                    ([], []) => Product::constant(Fraction::ZERO),
+                    ([], [n]) => Product::constant(*n),
+                    ([_], []) => sum.terms.pop().expect("a term"),
+                    // `y - x` and `x - y` share a factor, with opposite signs
+                    _ => {
+                        let sign = match (sum.terms.first(), sum.constants.first()) {
+                            (Some(term), _) => term.coefficient.numer.signum(),
+                            (None, Some(n)) => n.numer.signum(),
+                            (None, None) => 1.0,
+                        };
+                        if sign < 0.0 {
+                            sum = sum.neg();
+                        }
+                        let sign = Fraction::new(sign, 1.0).expect("a sign");
+                        Product::constant(sign).times(Product::atom(sum.into_expr(), 1.0))
+                    }
+                }
+            }
+            Expr::Binary(Op::Negate, ..) => unreachable!("negation isn't binary"),
+            Expr::Var(_) => Product::atom(expr.clone(), 1.0),
+            Expr::Call(name, args) => {
+                let args: Vec<_> = args.iter().map(Expr::simplify).collect();
+                let values: Option<Vec<_>> = args.iter().map(value).collect();
+                values
+                    .and_then(|values| f64::call(name, values).ok())
+                    .and_then(|n| Fraction::new(n, 1.0))
+                    .map_or_else(
+                        || Product::atom(Expr::Call(name.clone(), args), 1.0),
+                        Product::constant,
+                    )
+            }
+        };
+        product.normalized()
+    }
+
+    fn times(mut self, other: Product) -> Product {
+        match self.coefficient.mul(other.coefficient) {
+            Some(coefficient) => self.coefficient = coefficient,
+            None => self.factors.push((other.coefficient.to_expr(), 1.0)),
+        }
+        self.factors.extend(other.factors);
+        self
+    }
+
+    /// `None` if the power of the coefficient isn't finite, or for a fractional power of a
+    /// product that isn't just one factor, since `(x ^ 2) ^ 0.5` is `|x|` rather than `x`
+    fn pow(self, exponent: f64) -> Option<Product> {
+        if exponent == 0.0 {
+            return Some(Product::constant(Fraction::ONE));
+        }
+        if !exponent.is_finite() {
+            return None;
+        }
+        if exponent.fract() != 0.0 || exponent.abs() > f64::from(i32::MAX) {
+            let Fraction { numer, denom } = self.coefficient;
+            return match &self.factors[..] {
+                [] => Fraction::new((numer / denom).powf(exponent), 1.0).map(Product::constant),
+                [(base, power)] if *power == 1.0 && self.coefficient == Fraction::ONE => {
+                    Some(Product::atom(base.clone(), exponent))
+                }
+                _ => None,
+            };
+        }
+        Some(Product {
+            coefficient: self.coefficient.powi(exponent as i32)?,
+            factors: self
+                .factors
+                .into_iter()
+                .map(|(base, power)| (base, power * exponent))
+                .collect(),
+        })
+    }
+
+    /// Sorts the factors, numbers first, and combines the powers of equal ones
+    fn normalized(mut self) -> Product {
+        if self.coefficient.numer == 0.0 {
+            return Product::constant(self.coefficient);
+        }
+        self.factors
+            .sort_by_cached_key(|(base, _)| factor_key(base));
+        let mut factors: Vec<(Expr, f64)> = vec![];
+        for (base, exponent) in self.factors {
+            match factors.last_mut() {
+                Some((last, power)) if *last == base && number(&base).is_none() => {
+                    *power += exponent
+                }
+                _ => factors.push((base, exponent)),
+            }
+        }
+        factors.retain(|&(_, exponent)| exponent != 0.0);
+        self.factors = factors;
+        self
+    }
+
+    /// The sum of the exponents, to put higher powers first in a sum
+    fn degree(&self) -> f64 {
+        self.factors.iter().map(|(_, exponent)| exponent).sum()
+    }
+
+    fn into_expr(self) -> Expr {
+        let power = |(base, exponent): (Expr, f64)| match exponent.abs() {
+            1.0 => base,
+            exponent => Expr::binary(Op::Exponent, base, Expr::Number(exponent)),
+        };
+        let (numer, denom): (Vec<_>, Vec<_>) = self
+            .factors
+            .into_iter()
+            .partition(|&(_, exponent)| exponent > 0.0);
+        let mut numer: Vec<_> = numer.into_iter().map(power).collect();
+        let coefficient = self.coefficient.numer;
+        if numer.is_empty() || coefficient.abs() != 1.0 {
+            numer.insert(0, Expr::Number(coefficient));
+        } else if coefficient == -1.0 {
+            numer[0] = Expr::Neg(Box::new(numer[0].clone()));
+        }
+        let numer = multiplied(numer).expect("a factor");
+        let denom = (self.coefficient.denom != 1.0)
+            .then_some(Expr::Number(self.coefficient.denom))
+            .into_iter()
+            .chain(denom.into_iter().map(power));
+        match multiplied(denom) {
+            Some(denom) => Expr::binary(Op::Divide, numer, denom),
+            None => numer,
+        }
+    }
+}
+
+/// Numbers lead, sums trail, everything else goes by how it prints
+fn factor_key(base: &Expr) -> (u8, String) {
+    let rank = match base {
+        Expr::Number(_) => 0,
+        Expr::Binary(Op::Plus | Op::Minus, ..) => 2,
+        _ => 1,
+    };
+    (rank, base.to_string())
+}
+
+/// `terms + constants`, with constants that can't be added without overflowing kept apart
+#[derive(Debug, Default)]
+struct Sum {
+    terms: Vec<Product>,
+    constants: Vec<Fraction>,
+}
+
+impl Sum {
+    /// The sum of `expr`, canonical
+    fn of(expr: &Expr) -> Sum {
+        let mut sum = Sum::default();
+        sum.add(expr, false);
+        sum.normalized()
+    }
+
+    fn add(&mut self, expr: &Expr, negated: bool) {
+        match expr {
+            Expr::Binary(Op::Plus, lhs, rhs) => {
+                self.add(lhs, negated);
+                self.add(rhs, negated);
+            }
+            Expr::Binary(Op::Minus, lhs, rhs) => {
+                self.add(lhs, negated);
+                self.add(rhs, !negated);
+            }
+            Expr::Neg(operand) => self.add(operand, !negated),
+            _ => {
+                let mut product = Product::of(expr);
+                if negated {
+                    product.coefficient = product.coefficient.neg();
+                }
+                match &product.factors[..] {
+                    [] => self.constants.push(product.coefficient),
+                    // a multiple of a sum, like `-(x + 8)`, is spread over its terms
+                    [(sum @ Expr::Binary(Op::Plus | Op::Minus, ..), power)] if *power == 1.0 => {
+                        let scale = Product::constant(product.coefficient);
+                        for term in Sum::of(sum).into_terms() {
+                            let term = term.times(scale.clone());
+                            if term.is_constant() {
+                                self.constants.push(term.coefficient);
+                            } else {
+                                self.terms.push(term);
+                            }
+                        }
+                    }
+                    _ => self.terms.push(product),
+                }
+            }
+        }
+    }
+
+    fn into_terms(self) -> impl Iterator<Item = Product> {
+        let constants = self.constants.into_iter().map(Product::constant);
+        self.terms.into_iter().chain(constants)
+    }
+
+    fn neg(mut self) -> Sum {
+        for term in &mut self.terms {
+            term.coefficient = term.coefficient.neg();
+        }
+        for n in &mut self.constants {
+            *n = n.neg();
+        }
+        self
+    }
+
+    /// Combines like terms and constants, and sorts the terms by falling degree and then by
+    /// how they print
+    fn normalized(self) -> Sum {
+        let mut terms: Vec<Product> = vec![];
+        for term in self.terms {
+            let like = terms.iter_mut().find(|like| {
+                like.factors == term.factors && like.coefficient.add(term.coefficient).is_some()
+            });
+            match like {
+                Some(like) => {
+                    like.coefficient = like.coefficient.add(term.coefficient).expect("a sum")
+                }
+                None => terms.push(term),
+            }
+        }
+        terms.retain(|term| term.coefficient.numer != 0.0);
+        let mut keyed: Vec<_> = terms
+            .into_iter()
+            .map(|term| {
+                let factors = Product {
+                    coefficient: Fraction::ONE,
+                    factors: term.factors.clone(),
+                };
+                (term.degree(), factors.into_expr().to_string(), term)
+            })
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let terms = keyed.into_iter().map(|(_, _, term)| term).collect();
+        let mut constants: Vec<Fraction> = vec![];
+        for n in self.constants {
+            match constants.iter_mut().find(|c| c.add(n).is_some()) {
+                Some(c) => *c = c.add(n).expect("a sum"),
+                None => constants.push(n),
+            }
+        }
+        constants.retain(|n| n.numer != 0.0);
+        Sum { terms, constants }
+    }
+
+    /// Positive terms first so `-x + y` reads `y - x`, then the constants
+    fn into_expr(self) -> Expr {
+        let (positive, negative): (Vec<_>, Vec<_>) = self
+            .terms
+            .into_iter()
+            .partition(|term| term.coefficient.numer > 0.0);
+        let terms = positive
+            .into_iter()
+            .chain(negative)
+            .map(|term| (term.coefficient.numer < 0.0, term))
+            .chain(
+                self.constants
+                    .into_iter()
+                    .map(|n| (n.numer < 0.0, Product::constant(n))),
+            );
+        let mut sum: Option<Expr> = None;
+        for (negative, mut term) in terms {
+            sum = Some(match sum {
+                None => term.into_expr(),
+                Some(sum) => {
+                    if negative {
+                        term.coefficient = term.coefficient.neg();
+                    }
+                    let op = if negative { Op::Minus } else { Op::Plus };
+                    Expr::binary(op, sum, term.into_expr())
+                }
+            });
+        }
+        sum.unwrap_or(Expr::Number(0.0))
+    }
+}
+
+fn call(name: &str, arg: Expr) -> Expr {
+    Expr::Call(name.to_string(), vec![arg])
+}
+
+impl Expr {
+    /// The expression with constants folded, identities applied and terms in canonical order
+    pub fn simplify(&self) -> Expr {
+        Sum::of(self).into_expr()
+    }
+
+    /// The simplified expression printed with as few parentheses as it needs, so expressions
+    /// that only differ in ways simplifying undoes print the same
+    pub fn pretty(&self) -> String {
+        self.simplify().to_string()
+    }
+
+    /// Whether `var` occurs in the expression
+    pub fn depends_on(&self, var: &str) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Var(name) => name == var,
+            Expr::Neg(operand) => operand.depends_on(var),
+            Expr::Binary(_, lhs, rhs) => lhs.depends_on(var) || rhs.depends_on(var),
+            Expr::Call(_, args) => args.iter().any(|arg| arg.depends_on(var)),
+        }
+    }
+
+    /// The simplified derivative with respect to `var`. Knows the functions of
+    /// [`OpTable::arithmetic`](crate::shunting_yard::OpTable::arithmetic) but `min` and `max`.
+    pub fn derivative(&self, var: &str) -> Result<Expr, NoDerivative> {
+        Ok(self.differentiate(var)?.simplify())
+    }
+
+    fn differentiate(&self, var: &str) -> Result<Expr, NoDerivative> {
+        if !self.depends_on(var) {
+            return Ok(Expr::Number(0.0));
+        }
+        let d = |expr: &Expr| expr.differentiate(var);
+        let product = |a, b| Expr::binary(Op::Multiply, a, b);
+        Ok(match self {
+            Expr::Number(_) => Expr::Number(0.0),
+            Expr::Var(_) => Expr::Number(1.0),
+            Expr::Neg(operand) => Expr::Neg(Box::new(d(operand)?)),
+            Expr::Binary(op @ (Op::Plus | Op::Minus), u, v) => Expr::binary(*op, d(u)?, d(v)?),
+            Expr::Binary(Op::Multiply, u, v) => Expr::binary(
+                Op::Plus,
+                product(d(u)?, (**v).clone()),
+                product((**u).clone(), d(v)?),
+            ),
+            Expr::Binary(Op::Divide, u, v) => Expr::binary(
+                Op::Divide,
+                Expr::binary(
+                    Op::Minus,
+                    product(d(u)?, (**v).clone()),
+                    product((**u).clone(), d(v)?),
+                ),
+                Expr::binary(Op::Exponent, (**v).clone(), Expr::Number(2.0)),
+            ),
+            // `v * u ^ (v - 1) * u'` for a constant exponent
+            Expr::Binary(Op::Exponent, u, v) if !v.depends_on(var) => product(
+                product(
+                    (**v).clone(),
+                    Expr::binary(
+                        Op::Exponent,
+                        (**u).clone(),
+                        Expr::binary(Op::Minus, (**v).clone(), Expr::Number(1.0)),
+                    ),
+                ),
+                d(u)?,
+            ),
+            // `u ^ v * (v' * ln(u) + v * u' / u)`
+            Expr::Binary(Op::Exponent, u, v) => product(
+                self.clone(),
+                Expr::binary(
+                    Op::Plus,
+                    product(d(v)?, call("ln", (**u).clone())),
+                    Expr::binary(Op::Divide, product((**v).clone(), d(u)?), (**u).clone()),
+                ),
+            ),
+            Expr::Binary(Op::Negate, ..) => unreachable!("negation isn't binary"),
+            Expr::Call(name, args) => {
+                let [u] = &args[..] else {
+                    return Err(NoDerivative(name.clone()));
+                };
+                let outer = match name.as_str() {
+                    "sin" => call("cos", u.clone()),
+                    "cos" => Expr::Neg(Box::new(call("sin", u.clone()))),
+                    "exp" => self.clone(),
+                    "ln" => Expr::binary(Op::Divide, Expr::Number(1.0), u.clone()),
+                    "sqrt" => Expr::binary(
+                        Op::Divide,
+                        Expr::Number(1.0),
+                        product(Expr::Number(2.0), self.clone()),
+                    ),
+                    "abs" => Expr::binary(Op::Divide, u.clone(), self.clone()),
+                    _ => return Err(NoDerivative(name.clone())),
+                };
+                product(outer, d(u)?)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn folds_constants() {
+        for (infix, simplified) in [
+            ("2 * 3 + 4 ^ 0.5", "8"),
+            ("x * (2 - 1)", "x"),
+            ("0 * (x + y) + y ^ (3 - 2)", "y"),
+            ("x + 0 - 0 / y", "x"),
+            ("x ^ 0 + 1 ^ x", "2"),
+            ("x - x", "0"),
+            ("(x + y) / (x + y)", "1"),
+            ("x / 3 + x / 6", "x / 2"),
+            ("1 / 3 - 1", "-2 / 3"),
+            ("--x", "x"),
+            ("-(x - y)", "y - x"),
+            ("x + -2", "x - 2"),
+            ("x - -y", "x + y"),
+            ("sqrt(16) * min(x, 1)", "4 * min(x, 1)"),
+            ("max(1, 2, 3) - sin(0)", "3"),
+            // nothing to fold into a finite number
+            ("1 / 0 + ln(0)", "1 / 0 + ln(0)"),
+            ("0 * -1", "0"),
+        ] {
+            assert_eq!(parse(infix).unwrap().pretty(), simplified, "{infix}");
+        }
+    }
+
+    #[test]
+    fn overflowing_constants() {
+        // sums and products that don't fold still get put in canonical order
+        let big = format!("1{}", "0".repeat(200));
+        let max = f64::MAX.to_string();
+        for (infix, simplified) in [
+            (format!("{big} * {big}"), format!("{big} * {big}")),
+            (format!("{max} + {max}"), format!("{max} + {max}")),
+            (format!("-{max} - {max}"), format!("-{max} - {max}")),
+            (format!("{max} - -{max}"), format!("{max} + {max}")),
+            (format!("x * {big} * {big}"), format!("{big} * {big} * x")),
+        ] {
+            assert_eq!(parse(&infix).unwrap().pretty(), simplified, "{infix}");
+        }
+    }
+
+    #[test]
+    fn canonical_order() {
+        for (infix, canonical) in [
+            ("x * 2 + 3 + 1", "2 * x + 4"),
+            ("4 + 2 * x", "2 * x + 4"),
+            ("2 * (3 * x)", "6 * x"),
+            ("(y * 3) * 2", "6 * y"),
+            ("x * (2 * y)", "2 * x * y"),
+            ("-x * 3", "-3 * x"),
+            ("1 + (x + 1)", "x + 2"),
+            ("x + 2 - 3", "x - 1"),
+            ("x - 1 + 1", "x"),
+            ("(x + 1) - 1", "x"),
+            ("2 * x + 3 * x", "5 * x"),
+            ("x * x / x ^ 3", "1 / x"),
+            ("3 * x ^ 3 + 2 * x - 5", "3 * x ^ 3 + 2 * x - 5"),
+            ("(y - x) * z", "-z * (x - y)"),
+        ] {
+            assert_eq!(parse(infix).unwrap().pretty(), canonical, "{infix}");
+            assert_eq!(parse(canonical).unwrap().pretty(), canonical, "{canonical}");
+        }
+    }
+
+    #[test]
+    fn same_canonical_form() {
+        for (a, b) in [
+            ("x + y", "y + x"),
+            ("x * y", "y * x"),
+            ("-(2 * x)", "x * -2"),
+            ("x / 2 / 3", "x / 6"),
+            ("x ^ -1", "1 / x"),
+            ("(x - y) * (y - x)", "-(y - x) ^ 2"),
+            ("sin(x) + 2 * x * y - 1", "y * x * 2 - (1 - sin(x))"),
+        ] {
+            assert_eq!(
+                parse(a).unwrap().pretty(),
+                parse(b).unwrap().pretty(),
+                "{a} vs {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn known_derivatives() {
+        for (f, df) in [
+            ("7", "0"),
+            ("y ^ 2", "0"),
+            ("x", "1"),
+            ("x ^ 2", "2 * x"),
+            ("3 * x ^ 3 + 2 * x - 5", "9 * x ^ 2 + 2"),
+            ("x ^ -1", "-1 / x ^ 2"),
+            ("1 / x", "-1 / x ^ 2"),
+            ("x * y", "y"),
+            ("sin(x) * x", "cos(x) * x + sin(x)"),
+            ("cos(2 * x)", "-2 * sin(2 * x)"),
+            ("exp(x ^ 2)", "2 * exp(x ^ 2) * x"),
+            ("ln(x)", "1 / x"),
+            ("sqrt(x)", "1 / (2 * sqrt(x))"),
+            ("y ^ x", "ln(y) * y ^ x"),
+            ("x ^ x", "x ^ x * (ln(x) + 1)"),
+            ("-x / y", "-1 / y"),
+        ] {
+            let derivative = parse(f).unwrap().derivative("x").unwrap();
+            assert_eq!(derivative.to_string(), df, "d/dx {f}");
+        }
+        assert_eq!(
+            parse("max(x, 1)").unwrap().derivative("x"),
+            Err(NoDerivative("max".to_string()))
+        );
+        assert_eq!(
+            parse("max(y, 1)").unwrap().derivative("x"),
+            Ok(Expr::Number(0.0))
+        );
+    }
+
+    #[test]
+    fn matches_finite_differences() {
+        let h = 1e-6;
+        for f in [
+            "x ^ 3 - 4 * x / (x + 1)",
+            "sin(x) ^ 2 + cos(x) ^ 2",
+            "exp(-x ^ 2 / 2) * ln(x)",
+            "x ^ sqrt(x)",
+            "abs(x - 2) * x",
+        ] {
+            let expr = parse(f).unwrap();
+            let derivative = expr.derivative("x").unwrap();
+            for x in [0.5, 1.3, 3.7] {
+                let at = |x: f64| HashMap::from([("x".to_string(), x)]);
+                let numeric =
+                    (expr.eval(&at(x + h)).unwrap() - expr.eval(&at(x - h)).unwrap()) / (2.0 * h);
+                let symbolic = derivative.eval(&at(x)).unwrap();
+                assert!(
+                    (numeric - symbolic).abs() < 1e-5,
+                    "d/dx {f} at {x}: {derivative}"
+                );
+            }
+        }
+    }
+}
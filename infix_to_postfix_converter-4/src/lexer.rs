@@ -0,0 +1,167 @@
+//! Tokens of an infix expression.
+//!
+//! Numbers are digits with an optional fractional part (`42`, `2.5`, `.5`), identifiers start
+//! with a letter or `_` and go on with letters, digits and `_`. Operator symbols come from an
+//! [`OpTable`], the longest one that fits wins, and identifiers that are symbols like `mod` are
+//! operators too. Whitespace only separates tokens. Telling a prefix `-` from a binary one is
+//! up to the parser.
+
+use std::fmt;
+use std::iter::{Enumerate, Peekable};
+use std::str::CharIndices;
+
+use crate::shunting_yard::OpTable;
+use crate::{ErrorKind, ParseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(String),
+    LeftPar,
+    RightPar,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{n}"),
+            Token::Ident(name) | Token::Symbol(name) => f.write_str(name),
+            Token::LeftPar => f.write_str("("),
+            Token::RightPar => f.write_str(")"),
+            Token::Comma => f.write_str(","),
+        }
+    }
+}
+
+/// A token along with the 1-based column of its first character
+pub type Spanned = (Token, usize);
+
+struct Lexer<'a> {
+    infix: &'a str,
+    /// Characters with their byte offsets, counted off to get columns
+    chars: Peekable<Enumerate<CharIndices<'a>>>,
+}
+
+impl Lexer<'_> {
+    /// Consumes characters while `f` holds and returns them
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some((_, (_, c))) = self.chars.next_if(|(_, (_, c))| f(*c)) {
+            text.push(c);
+        }
+        text
+    }
+}
+
+pub fn lex(infix: &str, table: &OpTable) -> Result<Vec<Spanned>, ParseError> {
+    let mut lexer = Lexer {
+        infix,
+        chars: infix.char_indices().enumerate().peekable(),
+    };
+    let mut tokens = vec![];
+    while let Some(&(i, (offset, ch))) = lexer.chars.peek() {
+        let column = i + 1;
+        let token = match ch {
+            c if c.is_whitespace() => {
+                lexer.chars.next();
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let text = lexer.eat_while(|c| c.is_ascii_digit() || c == '.');
+                let number = text
+                    .parse()
+                    .map_err(|_| ParseError::new(ErrorKind::BadNumber(text), column))?;
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let word = lexer.eat_while(|c| c.is_alphanumeric() || c == '_');
+                if table.is_symbol(&word) {
+                    Token::Symbol(word)
+                } else {
+                    Token::Ident(word)
+                }
+            }
+            '(' | ')' | ',' => {
+                lexer.chars.next();
+                match ch {
+                    '(' => Token::LeftPar,
+                    ')' => Token::RightPar,
+                    _ => Token::Comma,
+                }
+            }
+            c => {
+                let symbol = table
+                    .symbol_at(&lexer.infix[offset..])
+                    .ok_or_else(|| ParseError::new(ErrorKind::UnexpectedChar(c), column))?;
+                for _ in symbol.chars() {
+                    lexer.chars.next();
+                }
+                Token::Symbol(symbol.to_string())
+            }
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shunting_yard::Assoc;
+    use Token::*;
+
+    #[test]
+    fn tokens() {
+        let symbol = |s: &str| Symbol(s.to_string());
+        assert_eq!(
+            lex(" 12.5*(x_1 - .5)^ 3", &OpTable::arithmetic()).unwrap(),
+            [
+                (Number(12.5), 2),
+                (symbol("*"), 6),
+                (LeftPar, 7),
+                (Ident("x_1".to_string()), 8),
+                (symbol("-"), 12),
+                (Number(0.5), 14),
+                (RightPar, 16),
+                (symbol("^"), 17),
+                (Number(3.0), 19),
+            ]
+        );
+
+        let mut table = OpTable::default();
+        table
+            .binary("<", 1, Assoc::Left)
+            .binary("<=", 1, Assoc::Left)
+            .prefix("not", 2);
+        assert_eq!(
+            lex("not a<=b<c, é", &table).unwrap(),
+            [
+                (symbol("not"), 1),
+                (Ident("a".to_string()), 5),
+                (symbol("<="), 6),
+                (Ident("b".to_string()), 8),
+                (symbol("<"), 9),
+                (Ident("c".to_string()), 10),
+                (Comma, 11),
+                (Ident("é".to_string()), 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            lex("1 + 2.3.4", &OpTable::arithmetic()),
+            Err(ParseError::new(
+                ErrorKind::BadNumber("2.3.4".to_string()),
+                5
+            ))
+        );
+        assert_eq!(
+            lex("a % b", &OpTable::arithmetic()),
+            Err(ParseError::new(ErrorKind::UnexpectedChar('%'), 3))
+        );
+    }
+}
@@ -1,41 +1,51 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum Op {
-    LeftPar,
-    RightPar,
+mod expr;
+mod lexer;
+mod postfix;
+mod shunting_yard;
+#[cfg(test)]
+mod test_rng;
+
+use std::fmt;
+
+use expr::Expr;
+use lexer::Token;
+use shunting_yard::OpTable;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op {
     Plus,
     Minus,
     Multiply,
     Divide,
+    /// Unary minus, a `-` where an operand is expected
+    Negate,
     Exponent,
 }
 
 impl Op {
-    fn precedence(&self) -> u8 {
+    fn precedence(self) -> u8 {
         match self {
-            Op::LeftPar | Op::RightPar => 0,
             Op::Plus | Op::Minus => 1,
             Op::Multiply | Op::Divide => 2,
-            Op::Exponent => 3,
+            // `-2^2` is `-(2^2)` but `-2*3` is `(-2)*3`
+            Op::Negate => 3,
+            Op::Exponent => 4,
         }
     }
 
-    fn to_char(&self) -> char {
+    fn symbol(self) -> char {
         match self {
-            Op::LeftPar => '(',
-            Op::RightPar => ')',
             Op::Plus => '+',
             Op::Minus => '-',
             Op::Multiply => '*',
             Op::Divide => '/',
+            Op::Negate => '~',
             Op::Exponent => '^',
         }
     }
 
-    fn left_associative(&self) -> bool {
-        match self {
-            Op::Exponent => false,
-            _ => true,
-        }
+    fn left_associative(self) -> bool {
+        !matches!(self, Op::Exponent | Op::Negate)
     }
 }
 
@@ -44,8 +54,6 @@ impl TryFrom<char> for Op {
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
         match value {
-            '(' => Ok(Op::LeftPar),
-            ')' => Ok(Op::RightPar),
             '+' => Ok(Op::Plus),
             '-' => Ok(Op::Minus),
             '*' => Ok(Op::Multiply),
@@ -56,44 +64,152 @@ impl TryFrom<char> for Op {
     }
 }
 
-fn to_postfix(infix: &str) -> String {
-    let mut op_stack: Vec<Op> = vec![];
-    let mut output = String::new();
-    infix.chars().for_each(|c| {
-        if let Ok(op) = Op::try_from(c) {
-            match op {
-                Op::LeftPar => op_stack.push(op),
-                Op::RightPar => {
-                    while let Some(x) = op_stack.pop() {
-                        if x == Op::LeftPar {
-                            break;
-                        } else {
-                            output.push(x.to_char())
-                        }
-                    }
-                }
-                _ => {
-                    while let Some(x) = op_stack.last() {
-                        if x.precedence() > op.precedence()
-                            || (x.precedence() == op.precedence() && op.left_associative())
-                        {
-                            output.push(x.to_char());
-                            op_stack.pop();
-                        } else {
-                            break;
-                        }
-                    }
-                    op_stack.push(op);
-                }
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    BadNumber(String),
+    /// An operand where an operator was expected or the other way around
+    UnexpectedToken(Token),
+    UnexpectedEnd,
+    UnmatchedOpen,
+    UnmatchedClose,
+    /// A function called with a number of arguments it doesn't take
+    BadArity(String, usize),
+    /// A postfix token that is neither a number, a variable nor an operator
+    UnknownToken(String),
+    /// A postfix operator with too few operands before it
+    MissingOperand,
+    /// A postfix operand that no operator takes
+    ExtraOperand,
+}
+
+/// What went wrong and the 1-based column it went wrong at
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub column: usize,
+}
+
+impl ParseError {
+    fn new(kind: ErrorKind, column: usize) -> Self {
+        ParseError { kind, column }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: ", self.column)?;
+        match &self.kind {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character `{c}`"),
+            ErrorKind::BadNumber(text) => write!(f, "bad number `{text}`"),
+            ErrorKind::UnexpectedToken(Token::Number(n)) => write!(f, "unexpected number `{n}`"),
+            ErrorKind::UnexpectedToken(Token::Ident(name)) => {
+                write!(f, "unexpected identifier `{name}`")
             }
-        } else {
-            assert!(c.is_digit(10));
-            output.push(c);
+            ErrorKind::UnexpectedToken(token) => write!(f, "unexpected `{token}`"),
+            ErrorKind::UnexpectedEnd => f.write_str("unexpected end of expression"),
+            ErrorKind::UnmatchedOpen => f.write_str("unmatched `(`"),
+            ErrorKind::UnmatchedClose => f.write_str("unmatched `)`"),
+            ErrorKind::BadArity(name, 1) => write!(f, "`{name}` can't take 1 argument"),
+            ErrorKind::BadArity(name, args) => write!(f, "`{name}` can't take {args} arguments"),
+            ErrorKind::UnknownToken(token) => write!(f, "unknown token `{token}`"),
+            ErrorKind::MissingOperand => f.write_str("missing operand"),
+            ErrorKind::ExtraOperand => f.write_str("operand without an operator"),
         }
-    });
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses an infix expression in the language of `table`. Operators other than the ones of
+/// [`Op`] become calls named after their symbol.
+fn parse_with(table: &OpTable, infix: &str) -> Result<Expr, ParseError> {
+    table.to_rpn(infix).map(Expr::from_rpn)
+}
+
+/// Parses an arithmetic expression
+fn parse(infix: &str) -> Result<Expr, ParseError> {
+    parse_with(&OpTable::arithmetic(), infix)
+}
+
+/// # Panics
+///
+/// If `infix` isn't a valid expression.
+fn to_postfix(infix: &str) -> String {
+    match parse(infix) {
+        Ok(expr) => expr.postfix_tokens().concat(),
+        Err(e) => panic!("{e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_test(actual: &str, expected: &str) {
+        assert_eq!(
+            actual, expected,
+            "\nYour answer (left) is not the correct answer (right)"
+        )
+    }
+
+    #[test]
+    fn fixed_tests() {
+        do_test(&to_postfix("2+7*5"), "275*+");
+        do_test(&to_postfix("3*3/(7+1)"), "33*71+/");
+        do_test(&to_postfix("5+(6-2)*9+3^(7-1)"), "562-9*+371-^+");
+        do_test(&to_postfix("(5-4-1)+9/5/2-7/1/7"), "54-1-95/2/+71/7/-");
+        do_test(&to_postfix("1^2^3"), "123^^");
+    }
+
+    #[test]
+    fn tokens() {
+        let postfix = |infix| parse(infix).unwrap().postfix();
+        assert_eq!(postfix(" 12 +  x1*3.25 "), "12 x1 3.25 * +");
+        assert_eq!(postfix("-x - -2"), "x ~ 2 ~ -");
+        assert_eq!(postfix("-(-(a))"), "a ~ ~");
+        assert_eq!(postfix("2*-3^2"), "2 3 2 ^ ~ *");
+        assert_eq!(postfix("-2*3"), "2 ~ 3 *");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |infix| parse(infix).unwrap_err();
+        assert_eq!(
+            error("(1 + 2"),
+            ParseError::new(ErrorKind::UnmatchedOpen, 1)
+        );
+        assert_eq!(
+            error("1 + 2) * (3"),
+            ParseError::new(ErrorKind::UnmatchedClose, 6)
+        );
+        assert_eq!(
+            error("2 x"),
+            ParseError::new(ErrorKind::UnexpectedToken(Token::Ident("x".to_string())), 3)
+        );
+        assert_eq!(
+            error("2 * / 3"),
+            ParseError::new(
+                ErrorKind::UnexpectedToken(Token::Symbol("/".to_string())),
+                5
+            )
+        );
+        assert_eq!(
+            error("()"),
+            ParseError::new(ErrorKind::UnexpectedToken(Token::RightPar), 2)
+        );
+        assert_eq!(error("1 +"), ParseError::new(ErrorKind::UnexpectedEnd, 4));
+        assert_eq!(error(""), ParseError::new(ErrorKind::UnexpectedEnd, 1));
+        assert_eq!(
+            error("(1 + 2) ^ $").to_string(),
+            "column 11: unexpected character `$`"
+        );
+        assert_eq!(error("3 4").to_string(), "column 3: unexpected number `4`");
+    }
 
-    while let Some(op) = op_stack.pop() {
-        output.push(op.to_char())
+    #[test]
+    #[should_panic(expected = "column 1: unmatched `(`")]
+    fn invalid_infix() {
+        to_postfix("(1");
     }
-    output
 }
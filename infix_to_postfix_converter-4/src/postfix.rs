@@ -0,0 +1,506 @@
+//! Postfix notation read back: evaluated directly or rebuilt into infix.
+//!
+//! Tokens are separated by whitespace, so multi-digit numbers and variables can follow each
+//! other. Operators are `+`, `-`, `*`, `/`, `^` and `~` for negation, calls are a function name
+//! and how many arguments it takes, like `max/3`. Anything starting like a number is a number
+//! and anything else made of letters, digits and `_` a variable.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::expr::{EvalError, Expr};
+use crate::{ErrorKind, Op, ParseError};
+
+/// A number type postfix expressions can be evaluated in
+pub trait Number: Sized + Clone {
+    /// The number a token stands for, if it's one this type can hold
+    fn parse(token: &str) -> Option<Self>;
+
+    fn negate(self) -> Result<Self, EvalError>;
+
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Result<Self, EvalError>;
+
+    /// Applies the function `name`, none by default
+    fn call(name: &str, args: Vec<Self>) -> Result<Self, EvalError> {
+        Err(EvalError::UnknownFunction(name.to_string(), args.len()))
+    }
+}
+
+/// Integer arithmetic, division truncates toward zero and exponents can't be negative
+impl Number for i64 {
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+
+    fn negate(self) -> Result<Self, EvalError> {
+        self.checked_neg().ok_or(EvalError::Overflow)
+    }
+
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Result<Self, EvalError> {
+        match op {
+            Op::Plus => lhs.checked_add(rhs),
+            Op::Minus => lhs.checked_sub(rhs),
+            Op::Multiply => lhs.checked_mul(rhs),
+            Op::Divide if rhs == 0 => return Err(EvalError::DivisionByZero),
+            Op::Divide => lhs.checked_div(rhs),
+            Op::Exponent => {
+                let exponent = u32::try_from(rhs).map_err(|_| {
+                    if rhs < 0 {
+                        EvalError::BadExponent
+                    } else {
+                        EvalError::Overflow
+                    }
+                })?;
+                lhs.checked_pow(exponent)
+            }
+            Op::Negate => unreachable!("{op:?} isn't binary"),
+        }
+        .ok_or(EvalError::Overflow)
+    }
+}
+
+/// Floating point arithmetic, only division by zero is an error. Has the functions of
+/// [`OpTable::arithmetic`](crate::shunting_yard::OpTable::arithmetic).
+impl Number for f64 {
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+
+    fn negate(self) -> Result<Self, EvalError> {
+        Ok(-self)
+    }
+
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(match op {
+            Op::Plus => lhs + rhs,
+            Op::Minus => lhs - rhs,
+            Op::Multiply => lhs * rhs,
+            Op::Divide if rhs == 0.0 => return Err(EvalError::DivisionByZero),
+            Op::Divide => lhs / rhs,
+            Op::Exponent => lhs.powf(rhs),
+            Op::Negate => unreachable!("{op:?} isn't binary"),
+        })
+    }
+
+    fn call(name: &str, args: Vec<Self>) -> Result<Self, EvalError> {
+        match (name, &args[..]) {
+            ("abs", [x]) => Ok(x.abs()),
+            ("sqrt", [x]) => Ok(x.sqrt()),
+            ("ln", [x]) => Ok(x.ln()),
+            ("exp", [x]) => Ok(x.exp()),
+            ("sin", [x]) => Ok(x.sin()),
+            ("cos", [x]) => Ok(x.cos()),
+            ("min", [_, ..]) => Ok(args.into_iter().fold(f64::INFINITY, f64::min)),
+            ("max", [_, ..]) => Ok(args.into_iter().fold(f64::NEG_INFINITY, f64::max)),
+            _ => Err(EvalError::UnknownFunction(name.to_string(), args.len())),
+        }
+    }
+}
+
+/// An exact fraction in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+impl Rational {
+    /// # Panics
+    ///
+    /// If `denom` is zero or the fraction doesn't fit once reduced, like `i64::MIN / -1`.
+    pub fn new(numer: i64, denom: i64) -> Self {
+        Rational::reduced(numer.into(), denom.into())
+            .expect("a nonzero denominator")
+            .expect("a fraction of i64s fits once reduced")
+    }
+
+    /// `numer / denom` in lowest terms, `None` if `denom` is zero and `Some(Err)` if it
+    /// doesn't fit
+    fn reduced(numer: i128, denom: i128) -> Option<Result<Self, EvalError>> {
+        if denom == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (numer.unsigned_abs(), denom.unsigned_abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        let gcd = a.max(1) as i128 * denom.signum();
+        Some(
+            i64::try_from(numer / gcd)
+                .and_then(|numer| Ok((numer, i64::try_from(denom / gcd)?)))
+                .map(|(numer, denom)| Rational { numer, denom })
+                .map_err(|_| EvalError::Overflow),
+        )
+    }
+
+    fn checked(numer: i128, denom: i128) -> Result<Self, EvalError> {
+        Rational::reduced(numer, denom).unwrap_or(Err(EvalError::DivisionByZero))
+    }
+
+    fn pow(self, exponent: u32) -> Result<Self, EvalError> {
+        let numer = self
+            .numer
+            .checked_pow(exponent)
+            .ok_or(EvalError::Overflow)?;
+        let denom = self
+            .denom
+            .checked_pow(exponent)
+            .ok_or(EvalError::Overflow)?;
+        Ok(Rational { numer, denom })
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+/// Exact arithmetic where decimals like `0.1` are read as the fraction they spell out.
+/// Exponents have to be integers.
+impl Number for Rational {
+    fn parse(token: &str) -> Option<Self> {
+        let (whole, fraction) = token.split_once('.').unwrap_or((token, ""));
+        if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let denom = 10i64.checked_pow(fraction.len().try_into().ok()?)?;
+        let numer = format!("{whole}{fraction}").parse::<i64>().ok()?;
+        Some(Rational::new(numer, denom))
+    }
+
+    fn negate(self) -> Result<Self, EvalError> {
+        Rational::checked(-i128::from(self.numer), self.denom.into())
+    }
+
+    fn apply(op: Op, lhs: Self, rhs: Self) -> Result<Self, EvalError> {
+        let (a, b) = (i128::from(lhs.numer), i128::from(lhs.denom));
+        let (c, d) = (i128::from(rhs.numer), i128::from(rhs.denom));
+        match op {
+            Op::Plus => Rational::checked(a * d + c * b, b * d),
+            Op::Minus => Rational::checked(a * d - c * b, b * d),
+            Op::Multiply => Rational::checked(a * c, b * d),
+            Op::Divide => Rational::checked(a * d, b * c),
+            Op::Exponent if rhs.denom != 1 => Err(EvalError::BadExponent),
+            Op::Exponent => {
+                let exponent =
+                    u32::try_from(rhs.numer.unsigned_abs()).map_err(|_| EvalError::Overflow)?;
+                if rhs.numer < 0 {
+                    Rational::checked(lhs.denom.into(), lhs.numer.into())?.pow(exponent)
+                } else {
+                    lhs.pow(exponent)
+                }
+            }
+            Op::Negate => unreachable!("{op:?} isn't binary"),
+        }
+    }
+}
+
+/// A postfix token sorted out
+enum Item<'a> {
+    Number(&'a str),
+    Var(&'a str),
+    Op(Op),
+    Call(&'a str, usize),
+}
+
+/// The tokens of `postfix` with their 1-based columns
+fn items(postfix: &str) -> impl Iterator<Item = Result<(Item<'_>, usize), ParseError>> {
+    let mut column = 1;
+    let mut rest = postfix;
+    std::iter::from_fn(move || {
+        let skipped = rest.len() - rest.trim_start().len();
+        column += rest[..skipped].chars().count();
+        rest = &rest[skipped..];
+        let token = rest.split_whitespace().next()?;
+        rest = &rest[token.len()..];
+        let start = column;
+        column += token.chars().count();
+        let numeric = |c: char| c.is_ascii_digit() || c == '.';
+        let ident = |name: &str| {
+            name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        let item = match token {
+            "+" => Item::Op(Op::Plus),
+            "-" => Item::Op(Op::Minus),
+            "*" => Item::Op(Op::Multiply),
+            "/" => Item::Op(Op::Divide),
+            "^" => Item::Op(Op::Exponent),
+            "~" => Item::Op(Op::Negate),
+            _ if token
+                .strip_prefix('-')
+                .unwrap_or(token)
+                .starts_with(numeric) =>
+            {
+                Item::Number(token)
+            }
+            _ if ident(token) => Item::Var(token),
+            _ => match token.split_once('/') {
+                Some((name, args)) if ident(name) && args.parse::<usize>().is_ok() => {
+                    Item::Call(name, args.parse().expect("checked above"))
+                }
+                _ => {
+                    let kind = ErrorKind::UnknownToken(token.to_string());
+                    return Some(Err(ParseError::new(kind, start)));
+                }
+            },
+        };
+        Some(Ok((item, start)))
+    })
+}
+
+/// Runs `postfix` on a stack. Numbers are read with `number`, variables looked up with `var`,
+/// operators applied with `apply`, which gets a single operand for negation, and functions
+/// with `call`.
+fn fold<T, E: From<ParseError>>(
+    postfix: &str,
+    number: impl Fn(&str) -> Option<T>,
+    var: impl Fn(&str) -> Result<T, E>,
+    apply: impl Fn(Op, T, Option<T>) -> Result<T, E>,
+    call: impl Fn(&str, Vec<T>) -> Result<T, E>,
+) -> Result<T, E> {
+    let mut stack: Vec<(T, usize)> = vec![];
+    for item in items(postfix) {
+        let (item, column) = item?;
+        let value = match item {
+            Item::Number(token) => number(token)
+                .ok_or_else(|| ParseError::new(ErrorKind::BadNumber(token.to_string()), column))?,
+            Item::Var(name) => var(name)?,
+            Item::Op(op) => {
+                let missing = || ParseError::new(ErrorKind::MissingOperand, column);
+                let (rhs, _) = stack.pop().ok_or_else(missing)?;
+                if op == Op::Negate {
+                    apply(op, rhs, None)?
+                } else {
+                    let (lhs, _) = stack.pop().ok_or_else(missing)?;
+                    apply(op, lhs, Some(rhs))?
+                }
+            }
+            Item::Call(name, args) => {
+                let Some(start) = stack.len().checked_sub(args) else {
+                    return Err(ParseError::new(ErrorKind::MissingOperand, column).into());
+                };
+                let args = stack.drain(start..).map(|(arg, _)| arg).collect();
+                call(name, args)?
+            }
+        };
+        stack.push((value, column));
+    }
+    match stack.len() {
+        0 => {
+            let end = postfix.chars().count() + 1;
+            Err(ParseError::new(ErrorKind::UnexpectedEnd, end).into())
+        }
+        1 => Ok(stack.pop().expect("one value").0),
+        _ => Err(ParseError::new(ErrorKind::ExtraOperand, stack[0].1).into()),
+    }
+}
+
+/// The value of a whitespace separated postfix expression in the number type `T`, with its
+/// variables taken from `env`
+pub fn eval_postfix<T: Number>(postfix: &str, env: &HashMap<String, T>) -> Result<T, EvalError> {
+    fold(
+        postfix,
+        T::parse,
+        |name| {
+            env.get(name)
+                .cloned()
+                .ok_or_else(|| EvalError::UnboundVariable(name.to_string()))
+        },
+        |op, lhs, rhs| match rhs {
+            Some(rhs) => T::apply(op, lhs, rhs),
+            None => lhs.negate(),
+        },
+        T::call,
+    )
+}
+
+impl Expr {
+    /// Parses a whitespace separated postfix expression
+    pub fn from_postfix(postfix: &str) -> Result<Expr, ParseError> {
+        fold(
+            postfix,
+            |token| f64::parse(token).map(Expr::Number),
+            |name| Ok(Expr::Var(name.to_string())),
+            |op, lhs, rhs| {
+                Ok(match rhs {
+                    Some(rhs) => Expr::binary(op, lhs, rhs),
+                    None => Expr::Neg(Box::new(lhs)),
+                })
+            },
+            |name, args| Ok(Expr::Call(name.to_string(), args)),
+        )
+    }
+}
+
+/// Infix with as few parentheses as it takes to mean the same as `postfix`
+pub fn postfix_to_infix(postfix: &str) -> Result<String, ParseError> {
+    Expr::from_postfix(postfix).map(|expr| expr.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::test_rng::Rng;
+
+    fn random_expr(rng: &mut Rng, depth: u32) -> Expr {
+        let pick = if depth == 0 {
+            rng.below(2)
+        } else {
+            rng.below(6)
+        };
+        match pick {
+            0 => Expr::Number(rng.below(10) as f64),
+            1 => Expr::Var(["x", "y"][rng.below(2) as usize].to_string()),
+            2 => Expr::Neg(Box::new(random_expr(rng, depth - 1))),
+            _ => {
+                let ops = [Op::Plus, Op::Minus, Op::Multiply, Op::Divide, Op::Exponent];
+                let op = ops[rng.below(5) as usize];
+                let lhs = random_expr(rng, depth - 1);
+                Expr::binary(op, lhs, random_expr(rng, depth - 1))
+            }
+        }
+    }
+
+    #[test]
+    fn integers() {
+        let env = HashMap::from([("n".to_string(), 7)]);
+        let eval = |postfix| eval_postfix::<i64>(postfix, &env);
+        assert_eq!(eval("12 n 2 - *"), Ok(60));
+        assert_eq!(eval("n 2 /"), Ok(3));
+        assert_eq!(eval("n ~ 2 /"), Ok(-3));
+        // `2 ^ (3 ^ 2)`, not `(2 ^ 3) ^ 2`
+        assert_eq!(eval("2 3 2 ^ ^"), Ok(512));
+        assert_eq!(eval("2 3 ^ 2 ^"), Ok(64));
+        assert_eq!(eval("-3 2 ^"), Ok(9));
+        assert_eq!(eval("1 n n - /"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("2 1 ~ ^"), Err(EvalError::BadExponent));
+        assert_eq!(eval("2 63 ^"), Err(EvalError::Overflow));
+        assert_eq!(
+            eval("2.5 1 +").unwrap_err().to_string(),
+            "column 1: bad number `2.5`"
+        );
+        assert_eq!(
+            eval("m 1 +"),
+            Err(EvalError::UnboundVariable("m".to_string()))
+        );
+    }
+
+    #[test]
+    fn rationals() {
+        let env = HashMap::from([("half".to_string(), Rational::new(1, 2))]);
+        let eval = |postfix| eval_postfix::<Rational>(postfix, &env).map(|r| r.to_string());
+        assert_eq!(eval("1 3 / 1 6 / +"), Ok("1/2".to_string()));
+        assert_eq!(eval("0.1 0.2 +"), Ok("3/10".to_string()));
+        assert_eq!(eval("2 3 ~ ^"), Ok("1/8".to_string()));
+        assert_eq!(eval("half ~ 3 ^"), Ok("-1/8".to_string()));
+        assert_eq!(eval("6 4 / 2 *"), Ok("3".to_string()));
+        assert_eq!(eval("1 half half - /"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("0 1 ~ ^"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("4 half ^"), Err(EvalError::BadExponent));
+        assert_eq!(eval("10 19 ^ 10 *"), Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn floats() {
+        let eval = |postfix| eval_postfix::<f64>(postfix, &HashMap::new());
+        assert_eq!(eval("0.1 0.2 +"), Ok(0.1 + 0.2));
+        assert_eq!(eval("2 0.5 ~ ^"), Ok(2f64.powf(-0.5)));
+        assert_eq!(eval("1 0 /"), Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn malformed() {
+        let error = |postfix| eval_postfix::<i64>(postfix, &HashMap::new()).unwrap_err();
+        assert_eq!(
+            error("1 +"),
+            ParseError::new(ErrorKind::MissingOperand, 3).into()
+        );
+        assert_eq!(
+            error(" 1  2 3 +"),
+            ParseError::new(ErrorKind::ExtraOperand, 2).into()
+        );
+        assert_eq!(
+            error("1 2 %"),
+            ParseError::new(ErrorKind::UnknownToken("%".to_string()), 5).into()
+        );
+        assert_eq!(
+            error("1 ( +"),
+            ParseError::new(ErrorKind::UnknownToken("(".to_string()), 3).into()
+        );
+        assert_eq!(
+            error("  "),
+            ParseError::new(ErrorKind::UnexpectedEnd, 3).into()
+        );
+        assert_eq!(
+            postfix_to_infix("x y z"),
+            Err(ParseError::new(ErrorKind::ExtraOperand, 1))
+        );
+    }
+
+    #[test]
+    fn to_infix() {
+        assert_eq!(postfix_to_infix("1 2 + 3 *"), Ok("(1 + 2) * 3".to_string()));
+        assert_eq!(postfix_to_infix("a b c - -"), Ok("a - (b - c)".to_string()));
+        assert_eq!(postfix_to_infix("a b - c -"), Ok("a - b - c".to_string()));
+        assert_eq!(postfix_to_infix("a b ^ c ^"), Ok("(a ^ b) ^ c".to_string()));
+        assert_eq!(postfix_to_infix("a b c ^ ^"), Ok("a ^ b ^ c".to_string()));
+        assert_eq!(postfix_to_infix("a ~ b ^"), Ok("(-a) ^ b".to_string()));
+        assert_eq!(postfix_to_infix("x1 -2.5 /"), Ok("x1 / -2.5".to_string()));
+        assert_eq!(
+            postfix_to_infix("1 2 min/2 x max/2 ~"),
+            Ok("-max(min(1, 2), x)".to_string())
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let env = HashMap::new();
+        assert_eq!(
+            eval_postfix::<f64>("3 4 1 max/3 16 sqrt/1 -", &env),
+            Ok(0.0)
+        );
+        assert_eq!(
+            eval_postfix::<i64>("3 abs/1", &HashMap::new()),
+            Err(EvalError::UnknownFunction("abs".to_string(), 1))
+        );
+        assert_eq!(
+            eval_postfix::<f64>("1 2 sqrt/2", &env),
+            Err(EvalError::UnknownFunction("sqrt".to_string(), 2))
+        );
+        assert_eq!(
+            eval_postfix::<f64>("1 max/2", &env),
+            Err(ParseError::new(ErrorKind::MissingOperand, 3).into())
+        );
+        assert_eq!(
+            eval_postfix::<f64>("1 max/x", &env),
+            Err(ParseError::new(ErrorKind::UnknownToken("max/x".to_string()), 3).into())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let env = HashMap::from([("x".to_string(), 3.0), ("y".to_string(), -2.0)]);
+        for _ in 0..500 {
+            let expr = random_expr(&mut rng, 4);
+            let direct = expr.eval(&env).map(f64::to_bits);
+            let postfix = expr.postfix();
+            let infix = postfix_to_infix(&postfix).unwrap();
+            let reparsed = parse(&infix).unwrap();
+            assert_eq!(reparsed, expr, "{infix}");
+            assert_eq!(reparsed.eval(&env).map(f64::to_bits), direct, "{infix}");
+            assert_eq!(
+                eval_postfix(&postfix, &env).map(f64::to_bits),
+                direct,
+                "{postfix}"
+            );
+        }
+    }
+}
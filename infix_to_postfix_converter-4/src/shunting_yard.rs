@@ -0,0 +1,472 @@
+//! A table driven shunting-yard parser.
+//!
+//! An [`OpTable`] says which symbols are prefix and binary operators, how tightly they bind and
+//! which names are functions. [`OpTable::to_rpn`] turns infix text into reverse Polish notation
+//! with it and leaves what the operators mean to whoever consumes the result, so the same core
+//! parses arithmetic, boolean logic or any other small expression language. Function arguments
+//! go in parentheses, separated by commas.
+
+use std::collections::HashMap;
+
+use crate::lexer::{self, Token};
+use crate::{ErrorKind, Op, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Operator {
+    precedence: u8,
+    assoc: Assoc,
+}
+
+/// How many arguments a function takes, `max` is `None` for no upper bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Arity {
+    min: usize,
+    max: Option<usize>,
+}
+
+/// The operators and functions of an expression language
+#[derive(Debug, Clone, Default)]
+pub struct OpTable {
+    prefix: HashMap<String, u8>,
+    binary: HashMap<String, Operator>,
+    functions: HashMap<String, Arity>,
+}
+
+/// One step of an expression in reverse Polish notation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rpn {
+    Number(f64),
+    Var(String),
+    /// A prefix operator, applied to the value on top of the stack
+    Prefix(String),
+    /// A binary operator, applied to the two values on top of the stack
+    Binary(String),
+    /// A function applied to as many values off the top of the stack as it got arguments
+    Call(String, usize),
+}
+
+/// Something waiting on the operator stack
+enum Pending {
+    Open,
+    /// An open argument list, with the column of the function name and the number of commas
+    /// seen so far
+    Call(String, usize, usize),
+    Prefix(String, u8),
+    Binary(String, Operator),
+}
+
+impl Pending {
+    /// How tightly an operator binds, parentheses hold on to everything
+    fn precedence(&self) -> Option<u8> {
+        match self {
+            Pending::Open | Pending::Call(..) => None,
+            Pending::Prefix(_, precedence) => Some(*precedence),
+            Pending::Binary(_, operator) => Some(operator.precedence),
+        }
+    }
+}
+
+/// Symbols are words like `mod` or runs of punctuation like `<=`
+fn valid_symbol(symbol: &str) -> bool {
+    let word = symbol.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && symbol.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let punctuation = !symbol.is_empty()
+        && symbol.chars().all(|c| {
+            !(c.is_alphanumeric() || c.is_whitespace() || matches!(c, '_' | '.' | '(' | ')' | ','))
+        });
+    word || punctuation
+}
+
+impl OpTable {
+    /// `+`, `-`, `*`, `/` and `^` with the usual precedence, negation, and the functions
+    /// `abs`, `sqrt`, `ln`, `exp`, `sin`, `cos`, `min` and `max`
+    pub fn arithmetic() -> Self {
+        let mut table = OpTable::default();
+        for op in [Op::Plus, Op::Minus, Op::Multiply, Op::Divide, Op::Exponent] {
+            let assoc = if op.left_associative() {
+                Assoc::Left
+            } else {
+                Assoc::Right
+            };
+            table.binary(&op.symbol().to_string(), op.precedence(), assoc);
+        }
+        table
+            .prefix("-", Op::Negate.precedence())
+            .function("abs", 1)
+            .function("sqrt", 1)
+            .function("ln", 1)
+            .function("exp", 1)
+            .function("sin", 1)
+            .function("cos", 1)
+            .variadic("min", 1)
+            .variadic("max", 1);
+        table
+    }
+
+    /// Adds a prefix operator or changes how one binds. A symbol can be both a prefix and a
+    /// binary operator, like `-`.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` is neither a word nor made of punctuation other than `(`, `)`, `,`, `.` and
+    /// `_`.
+    pub fn prefix(&mut self, symbol: &str, precedence: u8) -> &mut Self {
+        assert!(valid_symbol(symbol), "`{symbol}` can't be an operator");
+        self.prefix.insert(symbol.to_string(), precedence);
+        self
+    }
+
+    /// Adds a binary operator or changes how one binds
+    ///
+    /// # Panics
+    ///
+    /// Same as [`OpTable::prefix`].
+    pub fn binary(&mut self, symbol: &str, precedence: u8, assoc: Assoc) -> &mut Self {
+        assert!(valid_symbol(symbol), "`{symbol}` can't be an operator");
+        let operator = Operator { precedence, assoc };
+        self.binary.insert(symbol.to_string(), operator);
+        self
+    }
+
+    /// Adds a function of exactly `arity` arguments
+    pub fn function(&mut self, name: &str, arity: usize) -> &mut Self {
+        let arity = Arity {
+            min: arity,
+            max: Some(arity),
+        };
+        self.functions.insert(name.to_string(), arity);
+        self
+    }
+
+    /// Adds a function of at least `min_args` arguments
+    pub fn variadic(&mut self, name: &str, min_args: usize) -> &mut Self {
+        let arity = Arity {
+            min: min_args,
+            max: None,
+        };
+        self.functions.insert(name.to_string(), arity);
+        self
+    }
+
+    pub(crate) fn is_symbol(&self, text: &str) -> bool {
+        self.prefix.contains_key(text) || self.binary.contains_key(text)
+    }
+
+    /// The longest operator symbol `text` starts with
+    pub(crate) fn symbol_at<'a>(&'a self, text: &str) -> Option<&'a str> {
+        self.prefix
+            .keys()
+            .chain(self.binary.keys())
+            .filter(|symbol| text.starts_with(symbol.as_str()))
+            .max_by_key(|symbol| symbol.len())
+            .map(String::as_str)
+    }
+
+    /// Moves operators that bind at least as tightly as a binary operator with `precedence` and
+    /// `assoc` from the stack to the output
+    fn pop_tighter(
+        stack: &mut Vec<(Pending, usize)>,
+        output: &mut Vec<Rpn>,
+        precedence: u8,
+        assoc: Assoc,
+    ) {
+        while let Some(top) = stack.last().and_then(|(pending, _)| pending.precedence()) {
+            if top < precedence || (top == precedence && assoc == Assoc::Right) {
+                break;
+            }
+            match stack.pop() {
+                Some((Pending::Prefix(symbol, _), _)) => output.push(Rpn::Prefix(symbol)),
+                Some((Pending::Binary(symbol, _), _)) => output.push(Rpn::Binary(symbol)),
+                _ => unreachable!("only operators have a precedence"),
+            }
+        }
+    }
+
+    /// Parses `infix` into reverse Polish notation
+    pub fn to_rpn(&self, infix: &str) -> Result<Vec<Rpn>, ParseError> {
+        let mut stack: Vec<(Pending, usize)> = vec![];
+        let mut output = vec![];
+        // operands and operators have to take turns, `(` and prefix operators go where
+        // operands do
+        let mut expect_operand = true;
+        // right after the `(` of a function, where `)` closes an empty argument list
+        let mut no_args = false;
+        let mut tokens = lexer::lex(infix, self)?.into_iter();
+        while let Some((token, column)) = tokens.next() {
+            let unexpected =
+                |token| Err(ParseError::new(ErrorKind::UnexpectedToken(token), column));
+            let opens_call = match &token {
+                Token::Number(_) | Token::Ident(_) | Token::LeftPar if !expect_operand => {
+                    return unexpected(token)
+                }
+                Token::Number(n) => {
+                    output.push(Rpn::Number(*n));
+                    expect_operand = false;
+                    false
+                }
+                Token::Ident(name) if self.functions.contains_key(name) => match tokens.next() {
+                    Some((Token::LeftPar, open)) => {
+                        stack.push((Pending::Call(name.clone(), column, 0), open));
+                        true
+                    }
+                    Some((token, column)) => {
+                        return Err(ParseError::new(ErrorKind::UnexpectedToken(token), column))
+                    }
+                    None => {
+                        let end = infix.chars().count() + 1;
+                        return Err(ParseError::new(ErrorKind::UnexpectedEnd, end));
+                    }
+                },
+                Token::Ident(name) => {
+                    output.push(Rpn::Var(name.clone()));
+                    expect_operand = false;
+                    false
+                }
+                Token::LeftPar => {
+                    stack.push((Pending::Open, column));
+                    false
+                }
+                Token::Symbol(symbol) if expect_operand => match self.prefix.get(symbol) {
+                    Some(&precedence) => {
+                        stack.push((Pending::Prefix(symbol.clone(), precedence), column));
+                        false
+                    }
+                    None => return unexpected(token),
+                },
+                Token::Symbol(symbol) => match self.binary.get(symbol) {
+                    Some(&operator) => {
+                        let Operator { precedence, assoc } = operator;
+                        OpTable::pop_tighter(&mut stack, &mut output, precedence, assoc);
+                        stack.push((Pending::Binary(symbol.clone(), operator), column));
+                        expect_operand = true;
+                        false
+                    }
+                    None => return unexpected(token),
+                },
+                Token::Comma if expect_operand => return unexpected(token),
+                Token::Comma => {
+                    OpTable::pop_tighter(&mut stack, &mut output, 0, Assoc::Left);
+                    match stack.last_mut() {
+                        Some((Pending::Call(_, _, commas), _)) => *commas += 1,
+                        _ => return unexpected(token),
+                    }
+                    expect_operand = true;
+                    false
+                }
+                Token::RightPar if expect_operand && !no_args => return unexpected(token),
+                Token::RightPar => {
+                    OpTable::pop_tighter(&mut stack, &mut output, 0, Assoc::Left);
+                    match stack.pop() {
+                        Some((Pending::Open, _)) => {}
+                        Some((Pending::Call(name, name_column, commas), _)) => {
+                            let args = if no_args { 0 } else { commas + 1 };
+                            let arity = self.functions[&name];
+                            if args < arity.min || arity.max.is_some_and(|max| args > max) {
+                                let kind = ErrorKind::BadArity(name, args);
+                                return Err(ParseError::new(kind, name_column));
+                            }
+                            output.push(Rpn::Call(name, args));
+                        }
+                        _ => return Err(ParseError::new(ErrorKind::UnmatchedClose, column)),
+                    }
+                    expect_operand = false;
+                    false
+                }
+            };
+            no_args = opens_call;
+        }
+        if expect_operand {
+            let end = infix.chars().count() + 1;
+            return Err(ParseError::new(ErrorKind::UnexpectedEnd, end));
+        }
+        while let Some((pending, column)) = stack.pop() {
+            output.push(match pending {
+                Pending::Open | Pending::Call(..) => {
+                    return Err(ParseError::new(ErrorKind::UnmatchedOpen, column))
+                }
+                Pending::Prefix(symbol, _) => Rpn::Prefix(symbol),
+                Pending::Binary(symbol, _) => Rpn::Binary(symbol),
+            });
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Boolean logic with `!`, `&`, `|` and a right associative `->`, and a 3 way `if`
+    fn logic() -> OpTable {
+        let mut table = OpTable::default();
+        table
+            .prefix("!", 4)
+            .binary("&", 3, Assoc::Left)
+            .binary("|", 2, Assoc::Left)
+            .binary("->", 1, Assoc::Right)
+            .function("if", 3);
+        table
+    }
+
+    fn eval_logic(infix: &str, vars: &HashMap<&str, bool>) -> Result<bool, ParseError> {
+        let mut stack: Vec<bool> = vec![];
+        for step in logic().to_rpn(infix)? {
+            let value = match step {
+                Rpn::Var(name) => vars[name.as_str()],
+                Rpn::Prefix(_) => !stack.pop().unwrap(),
+                Rpn::Binary(symbol) => {
+                    let (b, a) = (stack.pop().unwrap(), stack.pop().unwrap());
+                    match symbol.as_str() {
+                        "&" => a && b,
+                        "|" => a || b,
+                        _ => !a || b,
+                    }
+                }
+                Rpn::Call(_, _) => {
+                    let (no, yes, cond) = (
+                        stack.pop().unwrap(),
+                        stack.pop().unwrap(),
+                        stack.pop().unwrap(),
+                    );
+                    if cond {
+                        yes
+                    } else {
+                        no
+                    }
+                }
+                Rpn::Number(_) => unreachable!(),
+            };
+            stack.push(value);
+        }
+        Ok(stack.pop().unwrap())
+    }
+
+    #[test]
+    fn custom_language() {
+        let postfix = |infix| {
+            logic()
+                .to_rpn(infix)
+                .unwrap()
+                .into_iter()
+                .map(|step| match step {
+                    Rpn::Var(name) | Rpn::Prefix(name) | Rpn::Binary(name) => name,
+                    Rpn::Call(name, args) => format!("{name}/{args}"),
+                    Rpn::Number(n) => n.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(postfix("!a & b | c"), "a ! b & c |");
+        assert_eq!(postfix("a -> b -> c"), "a b c -> ->");
+        assert_eq!(postfix("a->!(b|c)"), "a b c | ! ->");
+        assert_eq!(postfix("if(a, b & c, !a)"), "a b c & a ! if/3");
+
+        let vars = HashMap::from([("a", true), ("b", false), ("c", true)]);
+        for (infix, value) in [
+            ("a & !b", true),
+            ("b -> b -> b", true),
+            ("(b -> b) -> b", false),
+            ("if(b, a, c & !a)", false),
+            ("if(a | b, a -> c, b)", true),
+        ] {
+            assert_eq!(eval_logic(infix, &vars), Ok(value), "{infix}");
+        }
+        assert_eq!(
+            eval_logic("a & | b", &vars),
+            Err(ParseError::new(
+                ErrorKind::UnexpectedToken(Token::Symbol("|".to_string())),
+                5
+            ))
+        );
+        assert_eq!(
+            eval_logic("a !b", &vars),
+            Err(ParseError::new(
+                ErrorKind::UnexpectedToken(Token::Symbol("!".to_string())),
+                3
+            ))
+        );
+    }
+
+    #[test]
+    fn word_operators() {
+        let mut table = OpTable::arithmetic();
+        table
+            .binary("mod", 2, Assoc::Left)
+            .binary("**", 4, Assoc::Right);
+        assert_eq!(
+            table.to_rpn("a mod 2**3").unwrap(),
+            [
+                Rpn::Var("a".to_string()),
+                Rpn::Number(2.0),
+                Rpn::Number(3.0),
+                Rpn::Binary("**".to_string()),
+                Rpn::Binary("mod".to_string()),
+            ]
+        );
+        // `modulo` is a variable, not `mod` followed by `ulo`
+        assert_eq!(
+            table.to_rpn("modulo").unwrap(),
+            [Rpn::Var("modulo".to_string())]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let mut table = OpTable::arithmetic();
+        table.function("pi", 0);
+        assert_eq!(
+            table.to_rpn("max(1, -pi(), min(2, 3) * 4)").unwrap(),
+            [
+                Rpn::Number(1.0),
+                Rpn::Call("pi".to_string(), 0),
+                Rpn::Prefix("-".to_string()),
+                Rpn::Number(2.0),
+                Rpn::Number(3.0),
+                Rpn::Call("min".to_string(), 2),
+                Rpn::Number(4.0),
+                Rpn::Binary("*".to_string()),
+                Rpn::Call("max".to_string(), 3),
+            ]
+        );
+
+        let error = |infix| table.to_rpn(infix).unwrap_err();
+        assert_eq!(
+            error("1 + abs(2, 3)"),
+            ParseError::new(ErrorKind::BadArity("abs".to_string(), 2), 5)
+        );
+        assert_eq!(
+            error("max()"),
+            ParseError::new(ErrorKind::BadArity("max".to_string(), 0), 1)
+        );
+        assert_eq!(
+            error("abs 2"),
+            ParseError::new(ErrorKind::UnexpectedToken(Token::Number(2.0)), 5)
+        );
+        assert_eq!(error("sqrt"), ParseError::new(ErrorKind::UnexpectedEnd, 5));
+        assert_eq!(
+            error("(1, 2)"),
+            ParseError::new(ErrorKind::UnexpectedToken(Token::Comma), 3)
+        );
+        assert_eq!(
+            error("max(1,)"),
+            ParseError::new(ErrorKind::UnexpectedToken(Token::RightPar), 7)
+        );
+        assert_eq!(
+            error("min(1, 2"),
+            ParseError::new(ErrorKind::UnmatchedOpen, 4)
+        );
+        assert_eq!(error("(1 + 2))").to_string(), "column 8: unmatched `)`");
+    }
+
+    #[test]
+    #[should_panic(expected = "`a+` can't be an operator")]
+    fn mixed_symbol() {
+        OpTable::default().binary("a+", 1, Assoc::Left);
+    }
+}
@@ -0,0 +1,151 @@
+//! Arithmetic on numbers written as strings.
+//!
+//! Numbers are in a radix from 2 to 36 and may have a sign and a fractional part, like
+//! `-12.5` or `ff.8`. Results drop trailing fractional zeros and never come out as `-0`.
+
+use crate::big::{BigInt, BigUint, ParseBigIntError};
+
+/// `value / radix^scale`
+struct Fixed {
+    value: BigInt,
+    scale: usize,
+}
+
+impl Fixed {
+    fn parse(s: &str, radix: u32) -> Result<Fixed, ParseBigIntError> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let magnitude = BigUint::from_str_radix(&format!("{int}{frac}"), radix)?;
+        Ok(Fixed {
+            value: BigInt::new(negative, magnitude),
+            scale: frac.len(),
+        })
+    }
+
+    /// The value times `radix^(scale - self.scale)`
+    fn rescale(&self, scale: usize, radix: u32) -> BigInt {
+        let shift = u32::try_from(scale - self.scale).expect("a representable scale");
+        &self.value * &BigInt::from(BigUint::from(radix).pow(shift))
+    }
+
+    fn to_str_radix(&self, radix: u32) -> String {
+        let digits = self.value.magnitude().to_str_radix(radix);
+        let digits = format!("{digits:0>width$}", width = self.scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - self.scale);
+        let frac = frac.trim_end_matches('0');
+        let sign = if self.value.is_negative() { "-" } else { "" };
+        if frac.is_empty() {
+            format!("{sign}{int}")
+        } else {
+            format!("{sign}{int}.{frac}")
+        }
+    }
+}
+
+fn calculate(
+    a: &str,
+    b: &str,
+    radix: u32,
+    op: impl FnOnce(Fixed, Fixed) -> Fixed,
+) -> Result<String, ParseBigIntError> {
+    let result = op(Fixed::parse(a, radix)?, Fixed::parse(b, radix)?);
+    Ok(result.to_str_radix(radix))
+}
+
+pub fn add(a: &str, b: &str, radix: u32) -> Result<String, ParseBigIntError> {
+    calculate(a, b, radix, |a, b| {
+        let scale = a.scale.max(b.scale);
+        Fixed {
+            value: &a.rescale(scale, radix) + &b.rescale(scale, radix),
+            scale,
+        }
+    })
+}
+
+pub fn sub(a: &str, b: &str, radix: u32) -> Result<String, ParseBigIntError> {
+    calculate(a, b, radix, |a, b| {
+        let scale = a.scale.max(b.scale);
+        Fixed {
+            value: &a.rescale(scale, radix) - &b.rescale(scale, radix),
+            scale,
+        }
+    })
+}
+
+pub fn mul(a: &str, b: &str, radix: u32) -> Result<String, ParseBigIntError> {
+    calculate(a, b, radix, |a, b| Fixed {
+        value: &a.value * &b.value,
+        scale: a.scale + b.scale,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::Rng;
+
+    fn to_radix(n: i128, radix: u32) -> String {
+        let sign = if n < 0 { "-" } else { "" };
+        sign.to_string() + &BigUint::from(n.unsigned_abs()).to_str_radix(radix)
+    }
+
+    #[test]
+    fn decimals() {
+        assert_eq!(mul("12.5", "-0.04", 10), Ok("-0.5".to_string()));
+        assert_eq!(mul("1.50", "2", 10), Ok("3".to_string()));
+        assert_eq!(mul("-0", "5", 10), Ok("0".to_string()));
+        assert_eq!(mul("-0.001", "0.001", 10), Ok("-0.000001".to_string()));
+        assert_eq!(mul(".5", "4.", 10), Ok("2".to_string()));
+        assert_eq!(add("0.1", "0.2", 10), Ok("0.3".to_string()));
+        assert_eq!(add("-0.75", "0.7500", 10), Ok("0".to_string()));
+        assert_eq!(sub("1", "1.5", 10), Ok("-0.5".to_string()));
+        assert_eq!(sub(" +007.250 ", "-3", 10), Ok("10.25".to_string()));
+    }
+
+    #[test]
+    fn radixes() {
+        assert_eq!(mul("-101.1", "10", 2), Ok("-1011".to_string()));
+        assert_eq!(mul("0.1", "0.1", 2), Ok("0.01".to_string()));
+        assert_eq!(add("FF", "1", 16), Ok("100".to_string()));
+        assert_eq!(add("ff.8", "0.8", 16), Ok("100".to_string()));
+        assert_eq!(sub("z", "10", 36), Ok("-1".to_string()));
+        assert_eq!(mul("0.3", "3", 9), Ok("1".to_string()));
+    }
+
+    #[test]
+    fn against_i128() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..1000 {
+            let radix = rng.below(35) as u32 + 2;
+            let a = rng.below(1 << 40) as i128 - (1 << 39);
+            let b = rng.below(1 << 40) as i128 - (1 << 39);
+            let (x, y) = (to_radix(a, radix), to_radix(b, radix));
+            assert_eq!(add(&x, &y, radix), Ok(to_radix(a + b, radix)), "{x} + {y}");
+            assert_eq!(sub(&x, &y, radix), Ok(to_radix(a - b, radix)), "{x} - {y}");
+            assert_eq!(mul(&x, &y, radix), Ok(to_radix(a * b, radix)), "{x} * {y}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        use ParseBigIntError::*;
+        assert_eq!(mul("1 2", "3", 10), Err(InvalidDigit(' ')));
+        assert_eq!(mul("1.2.3", "3", 10), Err(InvalidDigit('.')));
+        assert_eq!(mul("3", "+-1", 10), Err(InvalidDigit('-')));
+        assert_eq!(add("2", "1", 2), Err(InvalidDigit('2')));
+        assert_eq!(add("1e5", "1", 10), Err(InvalidDigit('e')));
+        assert_eq!(sub("", "1", 10), Err(Empty));
+        assert_eq!(sub(".", "1", 10), Err(Empty));
+        assert_eq!(sub("1", "-", 10), Err(Empty));
+        assert_eq!(mul("1", "1", 1), Err(InvalidRadix(1)));
+        assert_eq!(mul("1", "1", 37), Err(InvalidRadix(37)));
+        assert_eq!(
+            InvalidRadix(37).to_string(),
+            "radix 37 isn't between 2 and 36"
+        );
+    }
+}
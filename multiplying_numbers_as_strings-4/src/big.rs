@@ -0,0 +1,766 @@
+//! Arbitrary precision integers.
+//!
+//! Magnitudes are stored as base 10^9 limbs, least significant first, so printing and parsing
+//! decimal strings is a matter of splitting them into runs of nine digits. There are never
+//! zero limbs at the top, zero has no limbs at all.
+
+mod mul;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+/// The base of a limb
+pub const BASE: u32 = 1_000_000_000;
+/// Decimal digits in a limb
+const DIGITS: usize = 9;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseBigIntError {
+    Empty,
+    InvalidDigit(char),
+    InvalidRadix(u32),
+}
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseBigIntError::Empty => f.write_str("no digits"),
+            ParseBigIntError::InvalidDigit(c) => write!(f, "invalid digit `{c}`"),
+            ParseBigIntError::InvalidRadix(radix) => {
+                write!(f, "radix {radix} isn't between 2 and 36")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+/// A non-negative integer of any size
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn from_limbs(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
+    }
+
+    /// Digits in `radix`, from 2 to 36, either case, leading zeros allowed
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseBigIntError> {
+        if !(2..=36).contains(&radix) {
+            return Err(ParseBigIntError::InvalidRadix(radix));
+        }
+        if radix == 10 {
+            return s.parse();
+        }
+        if s.is_empty() {
+            return Err(ParseBigIntError::Empty);
+        }
+        s.chars().try_fold(BigUint::default(), |n, c| {
+            let digit = c.to_digit(radix).ok_or(ParseBigIntError::InvalidDigit(c))?;
+            Ok(n.mul_limb(radix, digit))
+        })
+    }
+
+    /// Lowercase digits in `radix`
+    ///
+    /// # Panics
+    ///
+    /// If `radix` isn't from 2 to 36.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        assert!(
+            (2..=36).contains(&radix),
+            "radix {radix} isn't between 2 and 36"
+        );
+        if radix == 10 {
+            return self.to_string();
+        }
+        // peel off as many digits at once as a limb can hold
+        let (chunk, width) = std::iter::successors(Some((radix, 1)), |&(power, width)| {
+            Some((power.checked_mul(radix).filter(|&p| p < BASE)?, width + 1))
+        })
+        .last()
+        .expect("radix is below the base");
+        let mut digits = vec![];
+        let mut n = self.clone();
+        while !n.is_zero() {
+            let (quotient, mut rem) = n.div_rem_limb(chunk);
+            for _ in 0..width {
+                digits.push(char::from_digit(rem % radix, radix).expect("a digit below radix"));
+                rem /= radix;
+            }
+            n = quotient;
+        }
+        while digits.last() == Some(&'0') {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            return "0".to_string();
+        }
+        digits.iter().rev().collect()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// `self - other`, `None` if that would be negative
+    pub fn checked_sub(&self, other: &BigUint) -> Option<BigUint> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = self.limbs.clone();
+        let mut borrow = 0;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let sub = other.limbs.get(i).copied().unwrap_or(0) + borrow;
+            if sub == 0 && i >= other.limbs.len() {
+                break;
+            }
+            (*limb, borrow) = if *limb >= sub {
+                (*limb - sub, 0)
+            } else {
+                (*limb + BASE - sub, 1)
+            };
+        }
+        Some(BigUint::from_limbs(limbs))
+    }
+
+    /// `self * factor + carry` for a single limb factor
+    fn mul_limb(&self, factor: u32, carry: u32) -> BigUint {
+        let mut carry = u64::from(carry);
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        for &limb in &self.limbs {
+            let product = u64::from(limb) * u64::from(factor) + carry;
+            limbs.push((product % u64::from(BASE)) as u32);
+            carry = product / u64::from(BASE);
+        }
+        limbs.push(carry as u32);
+        BigUint::from_limbs(limbs)
+    }
+
+    /// Quotient and remainder of dividing by a single limb
+    fn div_rem_limb(&self, divisor: u32) -> (BigUint, u32) {
+        let mut rem = 0u64;
+        let mut limbs = vec![0; self.limbs.len()];
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            let current = rem * u64::from(BASE) + u64::from(limb);
+            limbs[i] = (current / u64::from(divisor)) as u32;
+            rem = current % u64::from(divisor);
+        }
+        (BigUint::from_limbs(limbs), rem as u32)
+    }
+
+    /// Quotient and remainder of `self / divisor`
+    ///
+    /// # Panics
+    ///
+    /// If `divisor` is zero.
+    pub fn div_rem(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+        assert!(!divisor.is_zero(), "attempt to divide by zero");
+        if *self < *divisor {
+            return (BigUint::default(), self.clone());
+        }
+        if let [limb] = divisor.limbs[..] {
+            let (quotient, rem) = self.div_rem_limb(limb);
+            return (quotient, BigUint::from(rem));
+        }
+        // Knuth's algorithm D. Scaling both sides so the divisor's top limb is at least half the
+        // base makes every quotient limb estimate from the top two limbs at most 2 too big.
+        let base = u64::from(BASE);
+        let scale = BASE / (divisor.limbs.last().expect("a multi-limb divisor") + 1);
+        let v = divisor.mul_limb(scale, 0).limbs;
+        let mut u = self.mul_limb(scale, 0).limbs;
+        u.resize(self.limbs.len() + 1, 0);
+        let n = v.len();
+        let (v_top, v_next) = (u64::from(v[n - 1]), u64::from(v[n - 2]));
+        let mut quotient = vec![0; u.len() - n];
+        for j in (0..quotient.len()).rev() {
+            let top = u64::from(u[j + n]) * base + u64::from(u[j + n - 1]);
+            let (mut q, mut r) = (top / v_top, top % v_top);
+            while q >= base || q * v_next > r * base + u64::from(u[j + n - 2]) {
+                q -= 1;
+                r += v_top;
+                if r >= base {
+                    break;
+                }
+            }
+            // u[j..=j + n] -= q * v
+            let (mut carry, mut borrow) = (0u64, 0i64);
+            for i in 0..n {
+                let product = q * u64::from(v[i]) + carry;
+                carry = product / base;
+                let diff = i64::from(u[i + j]) - (product % base) as i64 - borrow;
+                (u[i + j], borrow) = if diff < 0 {
+                    ((diff + base as i64) as u32, 1)
+                } else {
+                    (diff as u32, 0)
+                };
+            }
+            let diff = i64::from(u[j + n]) - carry as i64 - borrow;
+            if diff < 0 {
+                // q was one too big, add v back
+                q -= 1;
+                let mut carry = 0;
+                for i in 0..n {
+                    let sum = u[i + j] + v[i] + carry;
+                    (u[i + j], carry) = if sum >= BASE {
+                        (sum - BASE, 1)
+                    } else {
+                        (sum, 0)
+                    };
+                }
+                u[j + n] = (diff + base as i64 + i64::from(carry)) as u32 % BASE;
+            } else {
+                u[j + n] = diff as u32;
+            }
+            quotient[j] = q as u32;
+        }
+        u.truncate(n);
+        let (rem, _) = BigUint::from_limbs(u).div_rem_limb(scale);
+        (BigUint::from_limbs(quotient), rem)
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigUint {
+        let mut result = BigUint::from(1u32);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+}
+
+impl From<u32> for BigUint {
+    fn from(n: u32) -> Self {
+        BigUint::from(u128::from(n))
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint::from(u128::from(n))
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut n: u128) -> Self {
+        let mut limbs = vec![];
+        while n > 0 {
+            limbs.push((n % u128::from(BASE)) as u32);
+            n /= u128::from(BASE);
+        }
+        BigUint { limbs }
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = ParseBigIntError;
+
+    /// Decimal digits, leading zeros allowed
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseBigIntError::Empty);
+        }
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(ParseBigIntError::InvalidDigit(c));
+        }
+        let limbs = s
+            .as_bytes()
+            .rchunks(DIGITS)
+            .map(|chunk| chunk.iter().fold(0, |n, d| n * 10 + u32::from(d - b'0')))
+            .collect();
+        Ok(BigUint::from_limbs(limbs))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((top, rest)) = self.limbs.split_last() else {
+            return f.pad_integral(true, "", "0");
+        };
+        let mut digits = top.to_string();
+        for limb in rest.iter().rev() {
+            digits += &format!("{limb:09}");
+        }
+        f.pad_integral(true, "", &digits)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= rhs.limbs.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = 0;
+        for (i, &limb) in long.limbs.iter().enumerate() {
+            let sum = limb + short.limbs.get(i).copied().unwrap_or(0) + carry;
+            carry = u32::from(sum >= BASE);
+            limbs.push(sum - carry * BASE);
+        }
+        limbs.push(carry);
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    /// # Panics
+    ///
+    /// If `rhs` is bigger than `self`.
+    fn sub(self, rhs: &BigUint) -> BigUint {
+        self.checked_sub(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: &BigUint) -> BigUint {
+        if self.is_zero() || rhs.is_zero() {
+            return BigUint::default();
+        }
+        BigUint::from_limbs(mul::multiply(&self.limbs, &rhs.limbs))
+    }
+}
+
+impl Div for &BigUint {
+    type Output = BigUint;
+
+    fn div(self, rhs: &BigUint) -> BigUint {
+        self.div_rem(rhs).0
+    }
+}
+
+impl Rem for &BigUint {
+    type Output = BigUint;
+
+    fn rem(self, rhs: &BigUint) -> BigUint {
+        self.div_rem(rhs).1
+    }
+}
+
+/// A signed integer of any size, zero is never negative
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: BigUint,
+}
+
+impl BigInt {
+    pub fn new(negative: bool, magnitude: BigUint) -> Self {
+        BigInt {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn magnitude(&self) -> &BigUint {
+        &self.magnitude
+    }
+
+    /// Quotient rounded toward zero and the remainder that goes with it, which has the sign of
+    /// `self`, like the primitive integers
+    ///
+    /// # Panics
+    ///
+    /// If `divisor` is zero.
+    pub fn div_rem(&self, divisor: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, rem) = self.magnitude.div_rem(&divisor.magnitude);
+        (
+            BigInt::new(self.negative != divisor.negative, quotient),
+            BigInt::new(self.negative, rem),
+        )
+    }
+
+    pub fn pow(&self, exponent: u32) -> BigInt {
+        BigInt::new(
+            self.negative && exponent % 2 == 1,
+            self.magnitude.pow(exponent),
+        )
+    }
+}
+
+impl From<BigUint> for BigInt {
+    fn from(magnitude: BigUint) -> Self {
+        BigInt::new(false, magnitude)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(n: i128) -> Self {
+        BigInt::new(n < 0, BigUint::from(n.unsigned_abs()))
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    /// Decimal digits with an optional `+` or `-` in front
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        Ok(BigInt::new(negative, digits.parse()?))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude.to_string())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (negative, _) => {
+                if negative {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: &BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::new(self.negative, &self.magnitude + &rhs.magnitude);
+        }
+        // opposite signs, the bigger magnitude wins
+        match self.magnitude.checked_sub(&rhs.magnitude) {
+            Some(diff) => BigInt::new(self.negative, diff),
+            None => BigInt::new(rhs.negative, &rhs.magnitude - &self.magnitude),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: &BigInt) -> BigInt {
+        self + &-rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != rhs.negative,
+            &self.magnitude * &rhs.magnitude,
+        )
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, rhs: &BigInt) -> BigInt {
+        self.div_rem(rhs).0
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, rhs: &BigInt) -> BigInt {
+        self.div_rem(rhs).1
+    }
+}
+
+/// The same operators on owned values, by reference
+macro_rules! forward_owned {
+    ($($ty:ty: $($trait:ident $method:ident),*;)*) => {$($(
+        impl $trait for $ty {
+            type Output = $ty;
+
+            fn $method(self, rhs: $ty) -> $ty {
+                (&self).$method(&rhs)
+            }
+        }
+    )*)*};
+}
+
+forward_owned! {
+    BigUint: Add add, Sub sub, Mul mul, Div div, Rem rem;
+    BigInt: Add add, Sub sub, Mul mul, Div div, Rem rem;
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::Rng;
+
+    /// Any u64, with small numbers and limb boundaries coming up often
+    fn random_u64(rng: &mut Rng) -> u64 {
+        if rng.below(4) == 0 {
+            return 10u64.pow(rng.below(20) as u32) - rng.below(2);
+        }
+        let bits = rng.below(65) as u32;
+        rng.below(u64::MAX).checked_shr(64 - bits).unwrap_or(0)
+    }
+
+    fn random_i64(rng: &mut Rng) -> i128 {
+        let n = i128::from(random_u64(rng) >> 1);
+        if rng.below(2) == 0 {
+            n
+        } else {
+            -n
+        }
+    }
+
+    /// Digits of a number of up to `len` digits
+    fn random_digits(rng: &mut Rng, len: u64) -> String {
+        let len = rng.below(len) + 1;
+        (0..len)
+            .map(|_| char::from(b'0' + rng.below(10) as u8))
+            .collect()
+    }
+
+    fn big(n: u128) -> BigUint {
+        BigUint::from(n)
+    }
+
+    #[test]
+    fn parse_and_print() {
+        for s in [
+            "0",
+            "7",
+            "999999999",
+            "1000000000",
+            "123456789012345678901234567890",
+        ] {
+            assert_eq!(s.parse::<BigUint>().unwrap().to_string(), s);
+        }
+        assert_eq!("000000000000042".parse::<BigUint>(), Ok(big(42)));
+        assert_eq!("0000".parse::<BigUint>(), Ok(BigUint::default()));
+        assert_eq!("".parse::<BigUint>(), Err(ParseBigIntError::Empty));
+        assert_eq!(
+            "12a".parse::<BigUint>(),
+            Err(ParseBigIntError::InvalidDigit('a'))
+        );
+        assert_eq!(
+            "-12".parse::<BigUint>(),
+            Err(ParseBigIntError::InvalidDigit('-'))
+        );
+        assert_eq!("-0".parse::<BigInt>().unwrap().to_string(), "0");
+        assert_eq!("+15".parse::<BigInt>(), Ok(BigInt::from(15)));
+        assert_eq!("-".parse::<BigInt>(), Err(ParseBigIntError::Empty));
+        assert_eq!(
+            format!("{:>6}|{:<5}|{:+}", big(42), BigInt::from(-7), big(3)),
+            "    42|-7   |+3"
+        );
+    }
+
+    #[test]
+    fn radix() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for _ in 0..500 {
+            let radix = rng.below(35) as u32 + 2;
+            let n = u128::from(random_u64(&mut rng)) * u128::from(random_u64(&mut rng));
+            let s = BigUint::from(n).to_str_radix(radix);
+            assert_eq!(
+                u128::from_str_radix(&s, radix),
+                Ok(n),
+                "{n} in radix {radix}"
+            );
+            assert_eq!(
+                BigUint::from_str_radix(&s, radix),
+                Ok(big(n)),
+                "{s} in radix {radix}"
+            );
+        }
+        assert_eq!(BigUint::from_str_radix("00Ff", 16), Ok(big(255)));
+        assert_eq!(big(255).to_str_radix(2), "11111111");
+        assert_eq!(BigUint::default().to_str_radix(36), "0");
+        assert_eq!(
+            BigUint::from_str_radix("12", 2),
+            Err(ParseBigIntError::InvalidDigit('2'))
+        );
+        assert_eq!(BigUint::from_str_radix("", 3), Err(ParseBigIntError::Empty));
+        assert_eq!(
+            BigUint::from_str_radix("1", 37),
+            Err(ParseBigIntError::InvalidRadix(37))
+        );
+        assert_eq!(
+            BigUint::from_str_radix("1", 1),
+            Err(ParseBigIntError::InvalidRadix(1))
+        );
+    }
+
+    #[test]
+    fn unsigned_against_u128() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let (a, b) = (
+                u128::from(random_u64(&mut rng)),
+                u128::from(random_u64(&mut rng)),
+            );
+            let (x, y) = (big(a), big(b));
+            assert_eq!(&x + &y, big(a + b), "{a} + {b}");
+            assert_eq!(x.checked_sub(&y), a.checked_sub(b).map(big), "{a} - {b}");
+            assert_eq!(&x * &y, big(a * b), "{a} * {b}");
+            assert_eq!(x.cmp(&y), a.cmp(&b), "{a} <=> {b}");
+            assert_eq!(x.to_string(), a.to_string());
+            if b != 0 {
+                // divide a 128 bit number by a 1 to 3 limb one
+                let wide = a * b + rng.below(b as u64) as u128;
+                assert_eq!(
+                    big(wide).div_rem(&y),
+                    (big(wide / b), big(wide % b)),
+                    "{wide} / {b}"
+                );
+            }
+            let exponent = rng.below(5) as u32;
+            if let Some(power) = a.checked_pow(exponent) {
+                assert_eq!(x.pow(exponent), big(power), "{a} ^ {exponent}");
+            }
+        }
+    }
+
+    #[test]
+    fn signed_against_i128() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let (a, b) = (random_i64(&mut rng), random_i64(&mut rng));
+            let (x, y) = (BigInt::from(a), BigInt::from(b));
+            assert_eq!(&x + &y, BigInt::from(a + b), "{a} + {b}");
+            assert_eq!(&x - &y, BigInt::from(a - b), "{a} - {b}");
+            assert_eq!(&x * &y, BigInt::from(a * b), "{a} * {b}");
+            assert_eq!(x.cmp(&y), a.cmp(&b), "{a} <=> {b}");
+            assert_eq!(-&x, BigInt::from(-a));
+            assert_eq!(x.to_string(), a.to_string());
+            if b != 0 {
+                assert_eq!(
+                    x.div_rem(&y),
+                    (BigInt::from(a / b), BigInt::from(a % b)),
+                    "{a} / {b}"
+                );
+            }
+            let exponent = rng.below(3) as u32;
+            assert_eq!(
+                x.pow(exponent),
+                BigInt::from(a.pow(exponent)),
+                "{a} ^ {exponent}"
+            );
+        }
+    }
+
+    #[test]
+    fn long_division() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        for _ in 0..300 {
+            let a: BigUint = random_digits(&mut rng, 120).parse().unwrap();
+            let b: BigUint = random_digits(&mut rng, 60).parse().unwrap();
+            if b.is_zero() {
+                continue;
+            }
+            let (q, r) = a.div_rem(&b);
+            assert!(r < b, "{a} % {b} = {r}");
+            assert_eq!(&(&q * &b) + &r, a, "{a} / {b}");
+        }
+        // quotient limb estimates that need correcting
+        let a = big(10).pow(36) - big(1);
+        let b = big(10).pow(18) + big(1);
+        assert_eq!(
+            a.div_rem(&b),
+            (big(10).pow(18) - big(1), BigUint::default())
+        );
+        let b: BigUint = "500000000000000000000000001".parse().unwrap();
+        let a = &(&b * &big(999_999_999)) + &(&b - &big(1));
+        assert_eq!(a.div_rem(&b), (big(999_999_999), &b - &big(1)));
+    }
+
+    #[test]
+    fn owned_operators() {
+        let (a, b) = (big(12), big(5));
+        assert_eq!(a.clone() + b.clone(), big(17));
+        assert_eq!(a.clone() - b.clone(), big(7));
+        assert_eq!(a.clone() * b.clone(), big(60));
+        assert_eq!(a.clone() / b.clone(), big(2));
+        assert_eq!(a % b, big(2));
+        assert_eq!(-BigInt::from(-3) / BigInt::from(2), BigInt::from(1));
+        assert_eq!(BigInt::from(-7) % BigInt::from(2), BigInt::from(-1));
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to subtract with overflow")]
+    fn negative_unsigned() {
+        let _ = big(1) - big(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn division_by_zero() {
+        let _ = big(1) / BigUint::default();
+    }
+}
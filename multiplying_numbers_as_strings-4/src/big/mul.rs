@@ -0,0 +1,327 @@
+//! Multiplying limb slices, least significant limb first.
+//!
+//! Schoolbook multiplication is quickest for short operands, Karatsuba's three half-size
+//! products take over after a few dozen limbs and a number theoretic transform once both
+//! operands are past about 150,000 digits. The thresholds come from `bench_thresholds` below.
+//! Products are returned with `a.len() + b.len()` limbs, the top ones may be zero.
+
+use super::BASE;
+
+/// Limbs in the shorter operand from which Karatsuba beats schoolbook. On a single core Xeon
+/// Karatsuba took 11.6µs against 13.0µs at 64 limbs and 40.6µs against 58.2µs at 128, while
+/// cutting over at 32 limbs made 1024 limb products slower, 1.38ms against 1.18ms.
+const KARATSUBA_THRESHOLD: usize = 48;
+/// Limbs in the shorter operand from which the NTT beats Karatsuba. On the same machine the NTT
+/// took 54.8ms against 33.7ms at 8192 limbs but 107.6ms against 113.5ms at 16384.
+const NTT_THRESHOLD: usize = 16384;
+
+/// The product of `a` and `b` with whichever algorithm suits their size
+pub fn multiply(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let short = a.len().min(b.len());
+    if short < KARATSUBA_THRESHOLD {
+        schoolbook(a, b)
+    } else if short < NTT_THRESHOLD || (a.len() + b.len()) * DIGITS_PER_LIMB > NTT_MAX_LEN {
+        karatsuba(a, b)
+    } else {
+        ntt(a, b)
+    }
+}
+
+pub fn schoolbook(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let current = u64::from(product[i + j]) + u64::from(x) * u64::from(y) + carry;
+            product[i + j] = (current % u64::from(BASE)) as u32;
+            carry = current / u64::from(BASE);
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    product
+}
+
+pub fn karatsuba(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.len() < KARATSUBA_THRESHOLD {
+        return schoolbook(a, b);
+    }
+    let mut product = vec![0; a.len() + b.len()];
+    if 2 * short.len() <= long.len() {
+        // splitting in the middle would leave the short one without a top half, so go through the
+        // long one a short-sized piece at a time
+        for (i, piece) in long.chunks(short.len()).enumerate() {
+            add_into(&mut product[i * short.len()..], &karatsuba(piece, short));
+        }
+        return product;
+    }
+    // (a1 B^m + a0)(b1 B^m + b0) = z2 B^2m + z1 B^m + z0 with z1 = (a1 + a0)(b1 + b0) - z2 - z0
+    let m = long.len() / 2;
+    let (a0, a1) = a.split_at(m);
+    let (b0, b1) = b.split_at(m);
+    let z0 = trim(karatsuba(a0, b0));
+    let z2 = trim(karatsuba(a1, b1));
+    let mut z1 = karatsuba(&add(a0, a1), &add(b0, b1));
+    sub_from(&mut z1, &z0);
+    sub_from(&mut z1, &z2);
+    add_into(&mut product, &z0);
+    add_into(&mut product[m..], &trim(z1));
+    add_into(&mut product[2 * m..], &z2);
+    product
+}
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let mut sum = long.to_vec();
+    sum.push(0);
+    add_into(&mut sum, short);
+    sum
+}
+
+/// `acc += x`, the sum has to fit in `acc`
+fn add_into(acc: &mut [u32], x: &[u32]) {
+    let mut carry = 0;
+    for (i, limb) in acc.iter_mut().enumerate() {
+        if i >= x.len() && carry == 0 {
+            return;
+        }
+        let sum = *limb + x.get(i).copied().unwrap_or(0) + carry;
+        carry = u32::from(sum >= BASE);
+        *limb = sum - carry * BASE;
+    }
+    assert_eq!(carry, 0, "sum overflows its limbs");
+}
+
+/// `acc -= x`, `x` can't be bigger than `acc`
+fn sub_from(acc: &mut [u32], x: &[u32]) {
+    let mut borrow = 0;
+    for (i, limb) in acc.iter_mut().enumerate() {
+        if i >= x.len() && borrow == 0 {
+            return;
+        }
+        let sub = x.get(i).copied().unwrap_or(0) + borrow;
+        borrow = u32::from(*limb < sub);
+        *limb = *limb + borrow * BASE - sub;
+    }
+    assert_eq!(borrow, 0, "difference is negative");
+}
+
+/// An NTT friendly prime `k 2^n + 1` and a primitive root modulo it
+struct Prime {
+    modulus: u64,
+    root: u64,
+}
+
+/// Two primes whose product is well past the biggest convolution term, so each term can be
+/// recovered from its residues with the Chinese remainder theorem
+const PRIMES: [Prime; 2] = [
+    Prime {
+        modulus: 998_244_353,
+        root: 3,
+    },
+    Prime {
+        modulus: 2_013_265_921,
+        root: 31,
+    },
+];
+/// Limbs are split into smaller digits so convolution terms stay below the primes' product
+const DIGIT: u32 = 1000;
+const DIGITS_PER_LIMB: usize = 3;
+/// The longest transform both primes have roots of unity for, 998244353 = 119 * 2^23 + 1
+const NTT_MAX_LEN: usize = 1 << 23;
+
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1;
+    base %= modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+    result
+}
+
+/// In place iterative NTT of a power of two length
+fn transform(values: &mut [u64], prime: &Prime, inverse: bool) {
+    let (n, m) = (values.len(), prime.modulus);
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let mut step = pow_mod(prime.root, (m - 1) / len as u64, m);
+        if inverse {
+            step = pow_mod(step, m - 2, m);
+        }
+        for block in values.chunks_mut(len) {
+            let (lo, hi) = block.split_at_mut(len / 2);
+            let mut w = 1;
+            for (x, y) in lo.iter_mut().zip(hi) {
+                let (u, v) = (*x, *y * w % m);
+                *x = (u + v) % m;
+                *y = (u + m - v) % m;
+                w = w * step % m;
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        let n_inv = pow_mod(n as u64, m - 2, m);
+        for value in values {
+            *value = *value * n_inv % m;
+        }
+    }
+}
+
+/// # Panics
+///
+/// If the product has more than `NTT_MAX_LEN / 3` limbs.
+pub fn ntt(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let len = a.len() + b.len();
+    assert!(
+        len * DIGITS_PER_LIMB <= NTT_MAX_LEN,
+        "{len} limbs is too long for the NTT"
+    );
+    let digits = |limbs: &[u32]| -> Vec<u64> {
+        limbs
+            .iter()
+            .flat_map(|&limb| [limb % DIGIT, limb / DIGIT % DIGIT, limb / (DIGIT * DIGIT)])
+            .map(u64::from)
+            .collect()
+    };
+    let (x, y) = (digits(a), digits(b));
+    let n = (len * DIGITS_PER_LIMB).next_power_of_two();
+    let [r, s] = PRIMES.map(|prime| {
+        let (mut fx, mut fy) = (x.clone(), y.clone());
+        fx.resize(n, 0);
+        fy.resize(n, 0);
+        transform(&mut fx, &prime, false);
+        transform(&mut fy, &prime, false);
+        for (p, q) in fx.iter_mut().zip(&fy) {
+            *p = *p * q % prime.modulus;
+        }
+        transform(&mut fx, &prime, true);
+        fx
+    });
+    // t = r + p k with k = (s - r) / p mod q
+    let (p, q) = (PRIMES[0].modulus, PRIMES[1].modulus);
+    let p_inv = pow_mod(p, q - 2, q);
+    let mut carry = 0;
+    let mut product_digits = Vec::with_capacity(len * DIGITS_PER_LIMB);
+    for (&r, &s) in r.iter().zip(&s).take(len * DIGITS_PER_LIMB) {
+        let k = (s + q - r % q) % q * p_inv % q;
+        let term = r + p * k + carry;
+        product_digits.push((term % u64::from(DIGIT)) as u32);
+        carry = term / u64::from(DIGIT);
+    }
+    product_digits
+        .chunks(DIGITS_PER_LIMB)
+        .map(|d| d[0] + d[1] * DIGIT + d[2] * DIGIT * DIGIT)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::big::BigUint;
+    use crate::test_rng::Rng;
+    use std::time::Instant;
+
+    fn random_limbs(rng: &mut Rng, len: usize) -> Vec<u32> {
+        // all nines now and then, for the longest carry chains
+        let nines = rng.below(4) == 0;
+        (0..len)
+            .map(|_| {
+                if nines {
+                    BASE - 1
+                } else {
+                    rng.below(u64::from(BASE)) as u32
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_schoolbook() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..40 {
+            let (a_len, b_len) = (rng.below(1500), rng.below(1500));
+            let (a, b) = (
+                random_limbs(&mut rng, a_len as usize),
+                random_limbs(&mut rng, b_len as usize),
+            );
+            let expected = schoolbook(&a, &b);
+            assert_eq!(
+                karatsuba(&a, &b),
+                expected,
+                "karatsuba {} x {}",
+                a.len(),
+                b.len()
+            );
+            assert_eq!(ntt(&a, &b), expected, "ntt {} x {}", a.len(), b.len());
+            assert_eq!(multiply(&a, &b), expected, "{} x {}", a.len(), b.len());
+        }
+    }
+
+    #[test]
+    fn lopsided() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for (short, long) in [(0, 500), (1, 2000), (40, 1000), (33, 100), (400, 3000)] {
+            let (a, b) = (random_limbs(&mut rng, short), random_limbs(&mut rng, long));
+            let expected = schoolbook(&a, &b);
+            assert_eq!(karatsuba(&a, &b), expected, "karatsuba {short} x {long}");
+            assert_eq!(karatsuba(&b, &a), expected, "karatsuba {long} x {short}");
+            assert_eq!(multiply(&b, &a), expected, "{long} x {short}");
+        }
+    }
+
+    #[test]
+    fn hundred_thousand_digits() {
+        // (10^n - 1)^2 = 10^2n - 2 10^n + 1 = 9...980...01
+        let n = 100_000;
+        let nines: BigUint = "9".repeat(n).parse().unwrap();
+        let expected = "9".repeat(n - 1) + "8" + &"0".repeat(n - 1) + "1";
+        assert_eq!((&nines * &nines).to_string(), expected);
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_thresholds() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        for len in [16, 32, 48, 64, 128, 256, 1024, 4096, 8192, 16384, 32768] {
+            let (a, b) = (random_limbs(&mut rng, len), random_limbs(&mut rng, len));
+            let rounds = (1 << 22) / (len * len) + 1;
+            let mut times = vec![];
+            for algorithm in [schoolbook, karatsuba, ntt] {
+                let start = Instant::now();
+                for _ in 0..rounds {
+                    std::hint::black_box(algorithm(&a, &b));
+                }
+                times.push(start.elapsed() / rounds as u32);
+            }
+            println!(
+                "{len} limbs: schoolbook {:?}, karatsuba {:?}, ntt {:?}",
+                times[0], times[1], times[2]
+            );
+        }
+    }
+}
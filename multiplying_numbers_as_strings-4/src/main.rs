@@ -1,39 +1,22 @@
-use itertools::Itertools;
+mod arith;
+mod big;
+#[cfg(test)]
+mod test_rng;
 
+/// The product of two decimal numbers
+///
+/// # Panics
+///
+/// If either string isn't a decimal number.
 fn multiply(a: &str, b: &str) -> String {
-    let mut results: Vec<u32> = vec![0; a.len() + b.len()];
-    a.chars()
-        .rev()
-        .enumerate()
-        .cartesian_product(b.chars().rev().enumerate())
-        .for_each(|((i, ch1), (j, ch2))| {
-            results[i + j] += ch1.to_digit(10).unwrap() * ch2.to_digit(10).unwrap()
-        });
-
-    let mut carry: u32 = 0;
-    results.iter_mut().for_each(|v| {
-        *v += carry;
-        carry = *v / 10;
-        *v %= 10;
-    });
-
-    let res = results
-        .into_iter()
-        .rev()
-        .map(|v| char::from_digit(v, 10).unwrap())
-        .skip_while(|v| *v == '0')
-        .collect::<String>();
-    if res.len() == 0 {
-        "0".to_string()
-    } else {
-        res
-    }
+    arith::mul(a, b, 10).unwrap_or_else(|e| panic!("can't multiply `{a}` by `{b}`: {e}"))
 }
 
 // Add your tests here.
 // See https://doc.rust-lang.org/stable/rust-by-example/testing/unit_testing.html
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod sample_tests {
     use super::multiply;
 
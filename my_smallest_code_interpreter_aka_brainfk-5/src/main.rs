@@ -1,66 +1,147 @@
-use std::collections::HashMap;
+use esolang::brainfuck::{self, BfConfig, BfError};
+use esolang::error::Located;
 
 fn brain_luck(code: &str, input: Vec<u8>) -> Vec<u8> {
-    let mut input = input.into_iter();
-    let mut tape_ptr = 0;
-    let mut inst_ptr = 0;
-    let mut tape: HashMap<i32, u8> = HashMap::new();
-    let mut output: Vec<u8> = vec![];
-    let instructions: Vec<_> = code.chars().collect();
-    let brackets = {
-        let mut brackets_stack = vec![];
-        let mut brackets = HashMap::new();
-        for (i, ch) in instructions
-            .iter()
-            .enumerate()
-            .filter(|(_, c)| **c == '[' || **c == ']')
-        {
-            if *ch == ']' {
-                let (ch1, i1) = brackets_stack.pop().unwrap();
-                assert_eq!(ch1, '[');
-                brackets.insert(i1, i);
-                brackets.insert(i, i1);
-            } else {
-                brackets_stack.push((*ch, i))
-            }
-        }
-        assert_eq!(brackets_stack.len(), 0);
-        brackets
-    };
+    brain_luck_with(code, input, &BfConfig::default()).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Runs `code` with the conventions in `config`
+pub fn brain_luck_with(
+    code: &str,
+    input: Vec<u8>,
+    config: &BfConfig,
+) -> Result<Vec<u8>, Located<BfError>> {
+    brainfuck::try_run(code, input, config)
+}
 
-    while inst_ptr < instructions.len() {
-        match instructions[inst_ptr] {
-            '>' => tape_ptr += 1,
-            '<' => tape_ptr -= 1,
-            '+' => {
-                tape.entry(tape_ptr)
-                    .and_modify(|v| *v = v.overflowing_add(1).0)
-                    .or_insert(1);
-            }
+#[cfg(test)]
+mod tests {
+    use esolang::brainfuck::{CellSize, Eof, Generator, Overflow, TapeModel};
 
-            '-' => {
-                tape.entry(tape_ptr)
-                    .and_modify(|v| *v = v.overflowing_sub(1).0)
-                    .or_insert(255);
-            }
-            '.' => output.push(*tape.get(&tape_ptr).unwrap_or(&0)),
-            ',' => {
-                tape.insert(tape_ptr, input.next().unwrap());
-            }
-            '[' => {
-                if *tape.get(&tape_ptr).unwrap_or(&0) == 0 {
-                    inst_ptr = brackets[&inst_ptr];
-                }
-            }
+    use super::*;
+
+    fn ez_vec(s: &str, i: u8) -> Vec<u8> {
+        let mut v = s.to_string().into_bytes();
+        v.push(i);
+        v
+    }
+
+    #[test]
+    fn example_tests() {
+        // Echo until byte 255 encountered
+        assert_eq!(
+            String::from_utf8(brain_luck(",+[-.,+]", ez_vec("Codewars", 255))).unwrap(),
+            "Codewars"
+        );
+        // Echo until byte 0 encountered
+        assert_eq!(
+            String::from_utf8(brain_luck(",[.[-],]", ez_vec("Codewars", 0))).unwrap(),
+            "Codewars"
+        );
+        // Multiply two numbers
+        assert_eq!(
+            brain_luck(",>,<[>[->+>+<<]>>[-<<+>>]<<<-]>>.", vec![8, 9]),
+            vec![72]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            brain_luck("Echo one byte and add one: ,.+.", vec![b'a']),
+            b"ab"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1:5: read past the end of input")]
+    fn panics_with_position() {
+        brain_luck("++ ,, .", vec![1]);
+    }
+
+    #[test]
+    fn eof_modes() {
+        let run = |eof| {
+            let config = BfConfig {
+                eof,
+                ..Default::default()
+            };
+            brain_luck_with("+++,.", vec![], &config).map_err(|err| err.error)
+        };
+        assert_eq!(run(Eof::Unchanged), Ok(vec![3]));
+        assert_eq!(run(Eof::Zero), Ok(vec![0]));
+        assert_eq!(run(Eof::MinusOne), Ok(vec![255]));
+        assert_eq!(run(Eof::Error), Err(BfError::Eof));
+    }
+
+    #[test]
+    fn cell_sizes() {
+        // 256 is zero in a byte but not in wider cells
+        let code = "++++++++++++++++[>++++++++++++++++<-]>[[-]<+>]<.";
+        let run = |cell, overflow| {
+            let config = BfConfig {
+                cell,
+                overflow,
+                ..Default::default()
+            };
+            brain_luck_with(code, vec![], &config).map_err(|err| err.error)
+        };
+        assert_eq!(run(CellSize::U8, Overflow::Wrap), Ok(vec![0]));
+        assert_eq!(run(CellSize::U16, Overflow::Wrap), Ok(vec![1]));
+        assert_eq!(run(CellSize::U32, Overflow::Error), Ok(vec![1]));
+        assert_eq!(run(CellSize::U8, Overflow::Error), Err(BfError::Overflow));
+        let config = BfConfig {
+            overflow: Overflow::Error,
+            ..Default::default()
+        };
+        assert_eq!(
+            brain_luck_with("-", vec![], &config).map_err(|err| err.error),
+            Err(BfError::Overflow)
+        );
+    }
+
+    #[test]
+    fn tape_models() {
+        let run = |tape| {
+            let config = BfConfig {
+                tape,
+                ..Default::default()
+            };
+            brain_luck_with("+<++<+++>>>.", vec![], &config).map_err(|err| err.error)
+        };
+        assert_eq!(run(TapeModel::Infinite), Ok(vec![0]));
+        assert_eq!(run(TapeModel::Wrap(3)), Ok(vec![3]));
+        assert_eq!(run(TapeModel::Bounded(3)), Err(BfError::OutOfBounds));
+    }
 
-            ']' => {
-                if *tape.get(&tape_ptr).unwrap_or(&0) != 0 {
-                    inst_ptr = brackets[&inst_ptr];
-                }
-            }
-            _ => panic!("unknown instruction"),
-        }
-        inst_ptr += 1;
+    #[test]
+    fn generated_programs() {
+        // reads two numbers and prints their sum and product, wrapping around, or a warning if
+        // either is zero
+        let mut g = Generator::default();
+        g.read("a").read("b").set("ok", 1);
+        g.if_zero("a", |g| {
+            g.set("ok", 0);
+        });
+        g.if_zero("b", |g| {
+            g.set("ok", 0);
+        });
+        g.if_zero("ok", |g| {
+            g.print("zero!");
+        });
+        g.if_nonzero("ok", |g| {
+            g.copy("a", "sum").add("b", "sum").mul("a", "b", "product");
+            g.print_decimal("a")
+                .print(" + ")
+                .print_decimal("b")
+                .print(" = ");
+            g.print_decimal("sum")
+                .print(", product ")
+                .print_decimal("product");
+        });
+        let code = g.build();
+        assert_eq!(brain_luck(&code, vec![7, 9]), b"7 + 9 = 16, product 63");
+        assert_eq!(brain_luck(&code, vec![0, 9]), b"zero!");
+        assert_eq!(brain_luck(&code, vec![16, 16]), b"16 + 16 = 32, product 0");
     }
-    output
 }